@@ -9,19 +9,19 @@ use windows_kernel_rs::{Access, Driver, Error, kernel_module, KernelModule, Symb
 struct MyDevice;
 
 impl DeviceOperations for MyDevice {
-    fn create(&mut self, _device: &Device, request: IoRequest) -> Result<Completion, RequestError> {
+    fn create(&self, _device: &Device, _file: &(), request: IoRequest) -> Result<Completion, RequestError> {
         println!("userspace opened the device");
 
         Ok(Completion::Complete(0, request))
     }
 
-    fn close(&mut self, _device: &Device, request: IoRequest) -> Result<Completion, RequestError> {
+    fn close(&self, _device: &Device, _file: &(), request: IoRequest) -> Result<Completion, RequestError> {
         println!("userspace closed the device");
 
         Ok(Completion::Complete(0, request))
     }
 
-    fn cleanup(&mut self, _device: &Device, request: IoRequest) -> Result<Completion, RequestError> {
+    fn cleanup(&self, _device: &Device, _file: &(), request: IoRequest) -> Result<Completion, RequestError> {
         println!("device is no longer in use by userspace");
 
         Ok(Completion::Complete(0, request))
@@ -5,7 +5,8 @@ extern crate alloc;
 use alloc::vec;
 use alloc::vec::Vec;
 use windows_kernel_rs::device::{
-    Completion, Device, DeviceDoFlags, DeviceFlags, DeviceOperations, DeviceType, RequestError};
+    Completion, Device, DeviceDoFlags, DeviceFlags, DeviceType, RequestError, Serialized,
+    SerializedDeviceOperations};
 use windows_kernel_rs::request::{ReadRequest, WriteRequest};
 use windows_kernel_rs::{Access, Driver, Error, kernel_module, KernelModule, SymbolicLink};
 
@@ -13,8 +14,8 @@ struct MyDevice {
     data: Vec<u8>,
 }
 
-impl DeviceOperations for MyDevice {
-    fn read(&mut self, _device: &Device, request: ReadRequest) -> Result<Completion, RequestError> {
+impl SerializedDeviceOperations for MyDevice {
+    fn read(&mut self, _device: &Device, _file: &(), request: ReadRequest) -> Result<Completion, RequestError> {
         let mut user_ptr = request.user_ptr();
         let slice = user_ptr.as_mut_slice();
 
@@ -26,7 +27,7 @@ impl DeviceOperations for MyDevice {
         Ok(Completion::Complete(size as u32, request.into()))
     }
 
-    fn write(&mut self, _device: &Device, request: WriteRequest) -> Result<Completion, RequestError> {
+    fn write(&mut self, _device: &Device, _file: &(), request: WriteRequest) -> Result<Completion, RequestError> {
         let user_ptr = request.user_ptr();
 
         if request.offset() > 0 {
@@ -55,9 +56,9 @@ impl KernelModule for Module {
             DeviceFlags::SECURE_OPEN,
             DeviceDoFlags::DO_BUFFERED_IO,
             Access::NonExclusive,
-            MyDevice {
+            Serialized::new(MyDevice {
                 data: vec![],
-            },
+            }),
         )?;
         let symbolic_link = SymbolicLink::new("\\??\\Example", "\\Device\\Example")?;
 
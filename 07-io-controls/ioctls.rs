@@ -0,0 +1,13 @@
+// The I/O control codes of the driver. This file is included by both the driver and the user mode
+// application in `user/07-io-controls`, such that they always agree on the I/O control codes and
+// the types of their input and output.
+ioctl_table! {
+    pub ExampleIoctls: Unknown {
+        /// Prints the value to the kernel debugger.
+        print_value(0x800, ANY_ACCESS, Buffered): () => ();
+        /// Reads the value.
+        read_value(0x801, READ_DATA, Buffered): () => u32;
+        /// Writes the value.
+        write_value(0x802, WRITE_DATA, Buffered): u32 => ();
+    }
+}
@@ -1,56 +1,39 @@
 #![no_std]
 
+use core::sync::atomic::{AtomicU32, Ordering};
 use windows_kernel_rs::device::{
     Completion, Device, DeviceDoFlags, DeviceFlags, DeviceOperations, DeviceType, RequestError};
 use windows_kernel_rs::request::IoControlRequest;
-use windows_kernel_rs::{kernel_module, println};
-use windows_kernel_rs::{Access, Driver, Error, KernelModule, RequiredAccess, SymbolicLink};
+use windows_kernel_rs::{ioctl_table, kernel_module, println};
+use windows_kernel_rs::{Access, Driver, Error, KernelModule, SymbolicLink};
+
+include!("../ioctls.rs");
 
 struct MyDevice {
-    value: u32,
+    value: AtomicU32,
 }
 
-const IOCTL_PRINT_VALUE: u32 = 0x800;
-const IOCTL_READ_VALUE:  u32 = 0x801;
-const IOCTL_WRITE_VALUE: u32 = 0x802;
-
-impl MyDevice {
-    fn print_value(&mut self, _request: &IoControlRequest) -> Result<u32, Error> {
-        println!("value: {}", self.value);
+impl ExampleIoctls for MyDevice {
+    fn print_value(&self, _request: &IoControlRequest, _input: ()) -> Result<(), Error> {
+        println!("value: {}", self.value.load(Ordering::Relaxed));
 
-        Ok(0)
+        Ok(())
     }
 
-    fn read_value(&mut self, request: &IoControlRequest) -> Result<u32, Error> {
-        let mut user_ptr = request.user_ptr();
-
-        user_ptr.write(&self.value)?;
-
-        Ok(core::mem::size_of::<u32>() as u32)
+    fn read_value(&self, _request: &IoControlRequest, _input: ()) -> Result<u32, Error> {
+        Ok(self.value.load(Ordering::Relaxed))
     }
 
-    fn write_value(&mut self, request: &IoControlRequest) -> Result<u32, Error> {
-        let user_ptr = request.user_ptr();
-
-        self.value = user_ptr.read()?;
+    fn write_value(&self, _request: &IoControlRequest, value: u32) -> Result<(), Error> {
+        self.value.store(value, Ordering::Relaxed);
 
-        Ok(0)
+        Ok(())
     }
 }
 
 impl DeviceOperations for MyDevice {
-    fn ioctl(&mut self, _device: &Device, request: IoControlRequest) -> Result<Completion, RequestError> {
-        let result = match request.function() {
-            (_, IOCTL_PRINT_VALUE) =>
-                self.print_value(&request),
-            (RequiredAccess::READ_DATA, IOCTL_READ_VALUE) =>
-                self.read_value(&request),
-            (RequiredAccess::WRITE_DATA, IOCTL_WRITE_VALUE) =>
-                self.write_value(&request),
-            _ => Err(Error::INVALID_PARAMETER),
-        };
-
-        match result {
+    fn ioctl(&self, _device: &Device, _file: &(), request: IoControlRequest) -> Result<Completion, RequestError> {
+        match self.dispatch(&request) {
             Ok(size) => Ok(Completion::Complete(size, request.into())),
             Err(e) => Err(RequestError(e, request.into())),
         }
@@ -71,7 +54,7 @@ impl KernelModule for Module {
             DeviceDoFlags::DO_BUFFERED_IO,
             Access::NonExclusive,
             MyDevice {
-                value: 0,
+                value: AtomicU32::new(0),
             },
         )?;
         let symbolic_link = SymbolicLink::new("\\??\\Example", "\\Device\\Example")?;
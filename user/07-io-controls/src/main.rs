@@ -1,15 +1,8 @@
 use std::fs::OpenOptions;
 use std::os::windows::io::AsRawHandle;
-use winioctl::{DeviceType, Error};
-use winioctl::{ioctl_none, ioctl_read, ioctl_write};
+use winioctl::{ioctl_table, Error};
 
-const IOCTL_PRINT_VALUE: u32 = 0x800;
-const IOCTL_READ_VALUE:  u32 = 0x801;
-const IOCTL_WRITE_VALUE: u32 = 0x802;
-
-ioctl_none!(ioctl_print_value, DeviceType::Unknown, IOCTL_PRINT_VALUE);
-ioctl_read!(ioctl_read_value, DeviceType::Unknown, IOCTL_READ_VALUE, i32);
-ioctl_write!(ioctl_write_value, DeviceType::Unknown, IOCTL_WRITE_VALUE, i32);
+include!("../../../07-io-controls/ioctls.rs");
 
 fn main() -> Result<(), Error> {
     let file = OpenOptions::new()
@@ -17,21 +10,18 @@ fn main() -> Result<(), Error> {
         .write(true)
         .create(false)
         .open("\\??\\Example")?;
-    let mut value = 0;
 
-    unsafe {
-        ioctl_read_value(file.as_raw_handle(), &mut value)?;
-    }
+    // The file stays open for as long as the table exists.
+    let ioctls = unsafe {
+        ExampleIoctls::new(file.as_raw_handle())
+    };
 
-    value += 1;
+    let mut value = ioctls.read_value()?;
 
-    unsafe {
-        ioctl_write_value(file.as_raw_handle(), &value)?;
-    }
+    value += 1;
 
-    unsafe {
-        ioctl_print_value(file.as_raw_handle())?;
-    }
+    ioctls.write_value(&value)?;
+    ioctls.print_value()?;
 
     Ok(())
 }
@@ -0,0 +1,192 @@
+//! Parsing and formatting of GUIDs in the registry format, e.g.
+//! `{00000000-0000-0000-0000-000000000000}`.
+
+use core::fmt;
+
+/// The fields of a GUID, in the order of the `GUID` structure.
+pub type GuidFields = (u32, u16, u16, [u8; 8]);
+
+/// Parses a GUID in the registry format, e.g. `{00000000-0000-0000-0000-000000000000}`. The
+/// braces are optional and the hexadecimal digits are case insensitive. Returns `None` if the
+/// string is not a valid GUID.
+pub const fn parse(s: &str) -> Option<GuidFields> {
+    let bytes = s.as_bytes();
+
+    let start = match bytes.len() {
+        36 => 0,
+        38 if bytes[0] == b'{' && bytes[37] == b'}' => 1,
+        _ => return None,
+    };
+
+    // Collect the 32 hexadecimal digits, while checking the position of the dashes.
+    let mut digits = [0u8; 32];
+    let mut count = 0;
+    let mut i = 0;
+
+    while i < 36 {
+        let c = bytes[start + i];
+
+        if i == 8 || i == 13 || i == 18 || i == 23 {
+            if c != b'-' {
+                return None;
+            }
+        } else {
+            digits[count] = match hex_value(c) {
+                Some(value) => value,
+                None => return None,
+            };
+
+            count += 1;
+        }
+
+        i += 1;
+    }
+
+    let mut data1 = 0u32;
+    let mut data2 = 0u16;
+    let mut data3 = 0u16;
+    let mut data4 = [0u8; 8];
+    let mut i = 0;
+
+    while i < 8 {
+        data1 = (data1 << 4) | digits[i] as u32;
+        i += 1;
+    }
+
+    while i < 12 {
+        data2 = (data2 << 4) | digits[i] as u16;
+        i += 1;
+    }
+
+    while i < 16 {
+        data3 = (data3 << 4) | digits[i] as u16;
+        i += 1;
+    }
+
+    while i < 32 {
+        let index = (i - 16) / 2;
+        data4[index] = (data4[index] << 4) | digits[i];
+        i += 1;
+    }
+
+    Some((data1, data2, data3, data4))
+}
+
+const fn hex_value(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'a'..=b'f' => Some(c - b'a' + 10),
+        b'A'..=b'F' => Some(c - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Formats the fields of a GUID in the registry format, with braces and lowercase hexadecimal
+/// digits.
+pub fn format(f: &mut fmt::Formatter<'_>, fields: &GuidFields) -> fmt::Result {
+    let (data1, data2, data3, data4) = fields;
+
+    write!(
+        f,
+        "{{{:08x}-{:04x}-{:04x}-{:02x}{:02x}-",
+        data1, data2, data3, data4[0], data4[1],
+    )?;
+
+    for byte in &data4[2..] {
+        write!(f, "{:02x}", byte)?;
+    }
+
+    write!(f, "}}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::{String, ToString};
+
+    const FIELDS: GuidFields = (
+        0x5f3d8a3e,
+        0x2b1c,
+        0x4a8e,
+        [0x9d, 0x4f, 0x6c, 0x1b, 0x2a, 0x3d, 0x4e, 0x5f],
+    );
+
+    struct Display(GuidFields);
+
+    impl fmt::Display for Display {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            format(f, &self.0)
+        }
+    }
+
+    fn to_string(fields: GuidFields) -> String {
+        Display(fields).to_string()
+    }
+
+    #[test]
+    fn parse_with_braces() {
+        assert_eq!(parse("{5f3d8a3e-2b1c-4a8e-9d4f-6c1b2a3d4e5f}"), Some(FIELDS));
+    }
+
+    #[test]
+    fn parse_without_braces() {
+        assert_eq!(parse("5f3d8a3e-2b1c-4a8e-9d4f-6c1b2a3d4e5f"), Some(FIELDS));
+    }
+
+    #[test]
+    fn parse_is_case_insensitive() {
+        assert_eq!(parse("{5F3D8A3E-2B1C-4A8E-9D4F-6C1B2A3D4E5F}"), Some(FIELDS));
+        assert_eq!(parse("5f3D8a3E-2b1C-4a8E-9d4F-6c1B2a3D4e5F"), Some(FIELDS));
+    }
+
+    #[test]
+    fn parse_rejects_bad_lengths() {
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("{}"), None);
+        assert_eq!(parse("5f3d8a3e-2b1c-4a8e-9d4f-6c1b2a3d4e5"), None);
+        assert_eq!(parse("5f3d8a3e-2b1c-4a8e-9d4f-6c1b2a3d4e5f0"), None);
+        assert_eq!(parse("{5f3d8a3e-2b1c-4a8e-9d4f-6c1b2a3d4e5f"), None);
+        assert_eq!(parse("{5f3d8a3e-2b1c-4a8e-9d4f-6c1b2a3d4e5f}0"), None);
+    }
+
+    #[test]
+    fn parse_rejects_bad_braces() {
+        assert_eq!(parse("(5f3d8a3e-2b1c-4a8e-9d4f-6c1b2a3d4e5f)"), None);
+        assert_eq!(parse("}5f3d8a3e-2b1c-4a8e-9d4f-6c1b2a3d4e5f{"), None);
+    }
+
+    #[test]
+    fn parse_rejects_bad_hex() {
+        assert_eq!(parse("5f3d8a3g-2b1c-4a8e-9d4f-6c1b2a3d4e5f"), None);
+        assert_eq!(parse("5f3d8a3e-2b1c-4a8e-9d4f-6c1b2a3d4e5 "), None);
+        assert_eq!(parse("+f3d8a3e-2b1c-4a8e-9d4f-6c1b2a3d4e5f"), None);
+    }
+
+    #[test]
+    fn parse_rejects_misplaced_dashes() {
+        assert_eq!(parse("5f3d8a3e2-b1c-4a8e-9d4f-6c1b2a3d4e5f"), None);
+        assert_eq!(parse("5f3d8a3e-2b1c-4a8e-9d4f6-c1b2a3d4e5f"), None);
+        assert_eq!(parse("5f3d8a3e-2b1c-4a8e-9d4f-6c1b2a3d4e-5"), None);
+    }
+
+    #[test]
+    fn format_is_lowercase_with_braces() {
+        assert_eq!(to_string(FIELDS), "{5f3d8a3e-2b1c-4a8e-9d4f-6c1b2a3d4e5f}");
+        assert_eq!(to_string((0, 0, 0, [0; 8])), "{00000000-0000-0000-0000-000000000000}");
+    }
+
+    #[test]
+    fn round_trip() {
+        let s = "{0123abcd-ef01-2345-6789-abcdef012345}";
+
+        assert_eq!(to_string(parse(s).unwrap()), s);
+        assert_eq!(parse(&to_string(FIELDS)), Some(FIELDS));
+    }
+
+    #[test]
+    fn parse_in_const_context() {
+        const PARSED: Option<GuidFields> = parse("5f3d8a3e-2b1c-4a8e-9d4f-6c1b2a3d4e5f");
+
+        assert_eq!(PARSED, Some(FIELDS));
+    }
+}
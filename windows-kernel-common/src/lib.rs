@@ -0,0 +1,11 @@
+//! The parts of `windows-kernel-rs` that do not depend on the kernel, such that they can be
+//! shared with user mode and tested on the host.
+
+#![no_std]
+
+extern crate alloc;
+
+pub mod guid;
+pub mod pod;
+pub mod task;
+pub mod time;
@@ -0,0 +1,375 @@
+//! Plain old data, i.e. types that can be safely read from and written to the buffers of a
+//! request, both in the driver and in user mode.
+
+use alloc::vec::Vec;
+
+/// Marks types that consist of plain old data, i.e. types for which any bit pattern is a valid
+/// value and that do not contain any padding. Such types can be safely read from and written to
+/// the buffers of a request.
+///
+/// This can be derived through `#[derive(Pod)]` for structs that use `#[repr(C)]` or
+/// `#[repr(transparent)]`.
+///
+/// # Safety
+///
+/// Every bit pattern must be a valid value of the type, and the type must not contain padding.
+pub unsafe trait Pod: Copy + 'static {}
+
+macro_rules! impl_pod {
+    ($($ty:ty),*) => {
+        $(unsafe impl Pod for $ty {})*
+    };
+}
+
+impl_pod!((), u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+/// The error returned when a buffer does not fit the values that are read from or written to it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PodError {
+    /// The buffer is too small, or its size is not a multiple of the size of the values.
+    InvalidSize,
+    /// The buffer is not aligned for the values.
+    Misaligned,
+    /// The memory for the values could not be allocated.
+    OutOfMemory,
+}
+
+/// Returns the bytes of the value.
+pub fn as_bytes<T: Pod>(value: &T) -> &[u8] {
+    unsafe {
+        core::slice::from_raw_parts(value as *const T as *const u8, core::mem::size_of::<T>())
+    }
+}
+
+/// Returns the bytes of the values.
+pub fn slice_as_bytes<T: Pod>(values: &[T]) -> &[u8] {
+    unsafe {
+        core::slice::from_raw_parts(values.as_ptr() as *const u8, core::mem::size_of_val(values))
+    }
+}
+
+/// Reads a value from the start of the bytes. The bytes do not have to be aligned.
+pub fn read_unaligned<T: Pod>(bytes: &[u8]) -> Result<T, PodError> {
+    if bytes.len() < core::mem::size_of::<T>() {
+        return Err(PodError::InvalidSize);
+    }
+
+    Ok(unsafe { core::ptr::read_unaligned(bytes.as_ptr() as *const T) })
+}
+
+/// Reinterprets the bytes as a slice of values. The bytes must be aligned for `T` and the size
+/// must be a multiple of the size of `T`.
+pub fn cast_slice<T: Pod>(bytes: &[u8]) -> Result<&[T], PodError> {
+    let size = core::mem::size_of::<T>();
+
+    if size == 0 || !bytes.len().is_multiple_of(size) {
+        return Err(PodError::InvalidSize);
+    }
+
+    if !(bytes.as_ptr() as usize).is_multiple_of(core::mem::align_of::<T>()) {
+        return Err(PodError::Misaligned);
+    }
+
+    Ok(unsafe { core::slice::from_raw_parts(bytes.as_ptr() as *const T, bytes.len() / size) })
+}
+
+/// A cursor that reads values from a buffer, e.g. a header followed by a trailing array:
+///
+/// ```ignore
+/// let mut reader = Reader::new(user_ptr.as_slice());
+/// let header: Header = reader.read()?;
+/// let entries: Vec<Entry> = reader.read_vec(header.count as usize)?;
+/// ```
+pub struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            offset: 0,
+        }
+    }
+
+    /// Returns the number of bytes that have been read.
+    pub fn position(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the number of bytes that are left.
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.offset
+    }
+
+    /// Reads the given number of bytes.
+    pub fn read_bytes(&mut self, size: usize) -> Result<&'a [u8], PodError> {
+        if size > self.remaining() {
+            return Err(PodError::InvalidSize);
+        }
+
+        let bytes = &self.data[self.offset..self.offset + size];
+        self.offset += size;
+
+        Ok(bytes)
+    }
+
+    /// Skips the given number of bytes, e.g. to skip padding.
+    pub fn skip(&mut self, size: usize) -> Result<(), PodError> {
+        self.read_bytes(size).map(|_| ())
+    }
+
+    /// Reads a single value. The value does not have to be aligned.
+    pub fn read<T: Pod>(&mut self) -> Result<T, PodError> {
+        let bytes = self.read_bytes(core::mem::size_of::<T>())?;
+
+        read_unaligned(bytes)
+    }
+
+    /// Reads the given number of values without copying them. The values must be aligned.
+    pub fn read_slice<T: Pod>(&mut self, count: usize) -> Result<&'a [T], PodError> {
+        let size = count
+            .checked_mul(core::mem::size_of::<T>())
+            .ok_or(PodError::InvalidSize)?;
+
+        if size > self.remaining() {
+            return Err(PodError::InvalidSize);
+        }
+
+        let values = match size {
+            0 => &[],
+            _ => cast_slice(&self.data[self.offset..self.offset + size])?,
+        };
+
+        self.offset += size;
+
+        Ok(values)
+    }
+
+    /// Reads the given number of values into a vector. The values do not have to be aligned. The
+    /// vector is allocated fallibly, as the count usually comes from an untrusted buffer.
+    pub fn read_vec<T: Pod>(&mut self, count: usize) -> Result<Vec<T>, PodError> {
+        let size = count
+            .checked_mul(core::mem::size_of::<T>())
+            .ok_or(PodError::InvalidSize)?;
+
+        let bytes = self.read_bytes(size)?;
+        let size = core::mem::size_of::<T>();
+
+        let mut values = Vec::new();
+        values
+            .try_reserve_exact(count)
+            .map_err(|_| PodError::OutOfMemory)?;
+
+        for i in 0..count {
+            values.push(read_unaligned(&bytes[i * size..])?);
+        }
+
+        Ok(values)
+    }
+}
+
+/// A cursor that writes values to a buffer. The number of bytes written can be used to complete
+/// the request.
+///
+/// ```ignore
+/// let mut user_ptr = request.user_ptr();
+/// let mut writer = Writer::new(user_ptr.as_mut_slice());
+/// writer.write(&header)?;
+/// writer.write_partial(&entries);
+/// Ok(Completion::Complete(writer.position() as u32, request.into()))
+/// ```
+pub struct Writer<'a> {
+    data: &'a mut [u8],
+    offset: usize,
+}
+
+impl<'a> Writer<'a> {
+    pub fn new(data: &'a mut [u8]) -> Self {
+        Self {
+            data,
+            offset: 0,
+        }
+    }
+
+    /// Returns the number of bytes that have been written.
+    pub fn position(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the number of bytes that are left.
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.offset
+    }
+
+    /// Writes the bytes. Nothing is written if the bytes do not fit.
+    pub fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), PodError> {
+        if bytes.len() > self.remaining() {
+            return Err(PodError::InvalidSize);
+        }
+
+        self.data[self.offset..self.offset + bytes.len()].copy_from_slice(bytes);
+        self.offset += bytes.len();
+
+        Ok(())
+    }
+
+    /// Writes a single value. The value is written unaligned.
+    pub fn write<T: Pod>(&mut self, value: &T) -> Result<(), PodError> {
+        self.write_bytes(as_bytes(value))
+    }
+
+    /// Writes all of the values. Nothing is written if the values do not fit.
+    pub fn write_slice<T: Pod>(&mut self, values: &[T]) -> Result<(), PodError> {
+        self.write_bytes(slice_as_bytes(values))
+    }
+
+    /// Writes as many of the values as fit in the buffer, and returns the number of values that
+    /// have been written.
+    pub fn write_partial<T: Pod>(&mut self, values: &[T]) -> usize {
+        let size = core::mem::size_of::<T>();
+
+        let count = match size {
+            0 => values.len(),
+            _ => values.len().min(self.remaining() / size),
+        };
+
+        self.write_slice(&values[..count]).unwrap();
+
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    #[repr(C)]
+    struct Header {
+        count: u32,
+        flags: u32,
+    }
+
+    unsafe impl Pod for Header {}
+
+    #[repr(C, align(8))]
+    struct Aligned([u8; 32]);
+
+    const HEADER: Header = Header { count: 2, flags: 0x8000_0001 };
+
+    fn header_bytes() -> [u8; 8] {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(as_bytes(&HEADER));
+        bytes
+    }
+
+    #[test]
+    fn read_exact_length() {
+        let bytes = header_bytes();
+        let mut reader = Reader::new(&bytes);
+
+        assert_eq!(reader.read::<Header>(), Ok(HEADER));
+        assert_eq!(reader.position(), 8);
+        assert_eq!(reader.remaining(), 0);
+        assert_eq!(reader.read::<u8>(), Err(PodError::InvalidSize));
+    }
+
+    #[test]
+    fn read_short_buffer() {
+        let bytes = header_bytes();
+        let mut reader = Reader::new(&bytes[..7]);
+
+        assert_eq!(reader.read::<Header>(), Err(PodError::InvalidSize));
+        // A failed read does not advance the reader.
+        assert_eq!(reader.position(), 0);
+        assert_eq!(reader.read::<u32>(), Ok(2));
+        assert_eq!(reader.read::<u32>(), Err(PodError::InvalidSize));
+        assert_eq!(reader.read_bytes(3), Ok(&bytes[4..7]));
+    }
+
+    #[test]
+    fn read_unaligned_values() {
+        let mut buffer = Aligned([0; 32]);
+        buffer.0[1..9].copy_from_slice(&header_bytes());
+        buffer.0[9..17].copy_from_slice(&header_bytes());
+
+        let mut reader = Reader::new(&buffer.0);
+        reader.skip(1).unwrap();
+
+        assert_eq!(reader.read_vec::<Header>(2), Ok([HEADER, HEADER].to_vec()));
+        assert_eq!(reader.position(), 17);
+    }
+
+    #[test]
+    fn read_vec_short_buffer() {
+        let bytes = header_bytes();
+        let mut reader = Reader::new(&bytes);
+
+        assert_eq!(reader.read_vec::<u32>(3), Err(PodError::InvalidSize));
+        assert_eq!(reader.read_vec::<u32>(usize::MAX), Err(PodError::InvalidSize));
+        assert_eq!(reader.position(), 0);
+        assert_eq!(reader.read_vec::<u32>(2), Ok([2, 0x8000_0001].to_vec()));
+    }
+
+    #[test]
+    fn read_slice_alignment() {
+        let mut buffer = Aligned([0; 32]);
+        buffer.0[..8].copy_from_slice(&header_bytes());
+
+        let mut reader = Reader::new(&buffer.0);
+        assert_eq!(reader.read_slice::<Header>(1), Ok(&[HEADER][..]));
+
+        let mut reader = Reader::new(&buffer.0);
+        reader.skip(1).unwrap();
+        assert_eq!(reader.read_slice::<u32>(1), Err(PodError::Misaligned));
+        assert_eq!(reader.read_slice::<u32>(8), Err(PodError::InvalidSize));
+        assert_eq!(reader.read_slice::<u32>(0), Ok(&[][..]));
+        assert_eq!(reader.position(), 1);
+    }
+
+    #[test]
+    fn cast_slice_size_and_alignment() {
+        let buffer = Aligned([0; 32]);
+
+        assert_eq!(cast_slice::<u64>(&buffer.0).map(<[u64]>::len), Ok(4));
+        assert_eq!(cast_slice::<u64>(&buffer.0[..12]), Err(PodError::InvalidSize));
+        assert_eq!(cast_slice::<u64>(&buffer.0[4..12]), Err(PodError::Misaligned));
+        assert_eq!(cast_slice::<()>(&buffer.0), Err(PodError::InvalidSize));
+    }
+
+    #[test]
+    fn read_unaligned_short_buffer() {
+        assert_eq!(read_unaligned::<u64>(&[0; 7]), Err(PodError::InvalidSize));
+        assert_eq!(read_unaligned::<u16>(&[1, 0, 2]), Ok(1));
+    }
+
+    #[test]
+    fn write_overflow() {
+        let mut buffer = [0u8; 6];
+        let mut writer = Writer::new(&mut buffer);
+
+        assert_eq!(writer.write(&HEADER), Err(PodError::InvalidSize));
+        assert_eq!(writer.position(), 0);
+        assert_eq!(writer.write(&0x0201u16), Ok(()));
+        assert_eq!(writer.write_slice(&[3u16, 4]), Ok(()));
+        assert_eq!(writer.remaining(), 0);
+        assert_eq!(writer.write(&0u8), Err(PodError::InvalidSize));
+        assert_eq!(buffer, [1, 2, 3, 0, 4, 0]);
+    }
+
+    #[test]
+    fn write_partial() {
+        let mut buffer = [0u8; 7];
+        let mut writer = Writer::new(&mut buffer);
+
+        assert_eq!(writer.write_partial(&[1u16, 2, 3, 4]), 3);
+        assert_eq!(writer.position(), 6);
+        assert_eq!(writer.write_partial(&[5u16]), 0);
+        assert_eq!(writer.write_partial::<()>(&[(), ()]), 2);
+        assert_eq!(buffer, [1, 0, 2, 0, 3, 0, 0]);
+    }
+}
@@ -0,0 +1,342 @@
+//! The scheduling logic of the executor in `windows-kernel-rs`, which keeps track of whether a
+//! task has to be polled. The executors provide the [`Schedule`] implementations that run the
+//! tasks on system threads or work items.
+
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::task::Wake;
+use core::cell::UnsafeCell;
+use core::future::Future;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicU8, Ordering};
+use core::task::{Context, Waker};
+
+/// The task is waiting to be woken up.
+const IDLE: u8 = 0;
+/// The task has been handed to the scheduler, and is waiting to be run.
+const SCHEDULED: u8 = 1;
+/// The future of the task is being polled.
+const RUNNING: u8 = 2;
+/// The task has been woken up while its future was being polled, and has to be scheduled again
+/// once polling the future returns.
+const NOTIFIED: u8 = 3;
+/// The future of the task has completed and has been dropped.
+const COMPLETE: u8 = 4;
+
+/// Decides where and when the tasks that have been woken up are run, e.g. by queuing them to a
+/// dedicated thread or to a work item.
+pub trait Schedule: Send + Sync + Sized + 'static {
+    /// Hands the task to the scheduler, which must eventually call [`Task::run`] on it. A task is
+    /// only handed to the scheduler again once it has been run.
+    fn schedule(&self, task: Arc<Task<Self>>);
+
+    /// Called once the future of the task has completed.
+    fn completed(&self) {}
+}
+
+type BoxFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// A spawned future together with its scheduler. Waking up the task hands it to the scheduler,
+/// unless the task has already been handed to the scheduler, such that the future is polled by
+/// one thread at a time and every wake-up results in the future being polled at least once more.
+pub struct Task<S: Schedule> {
+    state: AtomicU8,
+    // The future is only accessed by whoever moved the task from `SCHEDULED` to `RUNNING`.
+    future: UnsafeCell<Option<BoxFuture>>,
+    scheduler: S,
+}
+
+unsafe impl<S: Schedule> Send for Task<S> {}
+unsafe impl<S: Schedule> Sync for Task<S> {}
+
+impl<S: Schedule> Task<S> {
+    /// Creates a task for the future, and hands it to the scheduler.
+    pub fn spawn<F>(scheduler: S, future: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let task = Arc::new(Self {
+            state: AtomicU8::new(SCHEDULED),
+            future: UnsafeCell::new(Some(Box::pin(future))),
+            scheduler,
+        });
+
+        task.scheduler.schedule(task.clone());
+    }
+
+    /// Returns the scheduler of the task.
+    pub fn scheduler(&self) -> &S {
+        &self.scheduler
+    }
+
+    /// Returns whether the future of the task has completed.
+    pub fn is_complete(&self) -> bool {
+        self.state.load(Ordering::Acquire) == COMPLETE
+    }
+
+    /// Polls the future of the task once. If the task gets woken up while the future is being
+    /// polled, the task is handed to the scheduler again afterwards.
+    ///
+    /// Must only be called by the scheduler, for a task that has been handed to it.
+    pub fn run(self: Arc<Self>) {
+        if self.state.compare_exchange(SCHEDULED, RUNNING, Ordering::AcqRel, Ordering::Acquire)
+            .is_err()
+        {
+            return;
+        }
+
+        let waker = Waker::from(self.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        // The task is in the `RUNNING` state, hence nobody else accesses the future.
+        let future = unsafe { &mut *self.future.get() };
+
+        let ready = match future.as_mut() {
+            Some(future) => future.as_mut().poll(&mut cx).is_ready(),
+            _ => true,
+        };
+
+        if ready {
+            *future = None;
+            self.state.store(COMPLETE, Ordering::Release);
+            self.scheduler.completed();
+            return;
+        }
+
+        if self.state.compare_exchange(RUNNING, IDLE, Ordering::AcqRel, Ordering::Acquire)
+            .is_err()
+        {
+            // The task was woken up while the future was being polled.
+            self.state.store(SCHEDULED, Ordering::Release);
+            self.scheduler.schedule(self.clone());
+        }
+    }
+}
+
+impl<S: Schedule> Wake for Task<S> {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        let mut state = self.state.load(Ordering::Acquire);
+
+        loop {
+            let next = match state {
+                IDLE => SCHEDULED,
+                RUNNING => NOTIFIED,
+                _ => return,
+            };
+
+            match self.state.compare_exchange_weak(state, next, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => break,
+                Err(actual) => state = actual,
+            }
+        }
+
+        if state == IDLE {
+            self.scheduler.schedule(self.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use alloc::vec::Vec;
+    use core::task::Poll;
+    use std::sync::Mutex;
+
+    /// A scheduler that queues the tasks, such that the tests decide when to run them.
+    #[derive(Clone, Default)]
+    struct Queue {
+        tasks: Arc<Mutex<Vec<Arc<Task<Queue>>>>>,
+        completed: Arc<Mutex<usize>>,
+    }
+
+    impl Schedule for Queue {
+        fn schedule(&self, task: Arc<Task<Self>>) {
+            self.tasks.lock().unwrap().push(task);
+        }
+
+        fn completed(&self) {
+            *self.completed.lock().unwrap() += 1;
+        }
+    }
+
+    impl Queue {
+        fn len(&self) -> usize {
+            self.tasks.lock().unwrap().len()
+        }
+
+        fn pop(&self) -> Arc<Task<Queue>> {
+            self.tasks.lock().unwrap().pop().expect("no task has been scheduled")
+        }
+
+        fn completed(&self) -> usize {
+            *self.completed.lock().unwrap()
+        }
+    }
+
+    /// The state shared between a test and the future of its task.
+    #[derive(Default)]
+    struct Shared {
+        polls: usize,
+        ready: bool,
+        // The number of times the future wakes itself up while being polled.
+        wakes_while_running: usize,
+        waker: Option<Waker>,
+    }
+
+    struct TestFuture(Arc<Mutex<Shared>>);
+
+    impl Future for TestFuture {
+        type Output = ();
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            let mut shared = self.0.lock().unwrap();
+
+            shared.polls += 1;
+            shared.waker = Some(cx.waker().clone());
+
+            for _ in 0..shared.wakes_while_running {
+                cx.waker().wake_by_ref();
+            }
+
+            match shared.ready {
+                true => Poll::Ready(()),
+                _ => Poll::Pending,
+            }
+        }
+    }
+
+    fn spawn() -> (Queue, Arc<Mutex<Shared>>) {
+        let queue = Queue::default();
+        let shared = Arc::new(Mutex::new(Shared::default()));
+
+        Task::spawn(queue.clone(), TestFuture(shared.clone()));
+
+        (queue, shared)
+    }
+
+    fn wake(shared: &Mutex<Shared>) {
+        let waker = shared.lock().unwrap().waker.clone().unwrap();
+
+        waker.wake();
+    }
+
+    #[test]
+    fn spawn_schedules_task() {
+        let (queue, shared) = spawn();
+
+        assert_eq!(queue.len(), 1);
+        assert_eq!(shared.lock().unwrap().polls, 0);
+
+        queue.pop().run();
+
+        assert_eq!(queue.len(), 0);
+        assert_eq!(shared.lock().unwrap().polls, 1);
+    }
+
+    #[test]
+    fn wake_while_idle() {
+        let (queue, shared) = spawn();
+        queue.pop().run();
+
+        wake(&shared);
+
+        assert_eq!(queue.len(), 1);
+
+        queue.pop().run();
+
+        assert_eq!(queue.len(), 0);
+        assert_eq!(shared.lock().unwrap().polls, 2);
+    }
+
+    #[test]
+    fn wake_while_scheduled() {
+        let (queue, shared) = spawn();
+        queue.pop().run();
+
+        wake(&shared);
+        wake(&shared);
+
+        assert_eq!(queue.len(), 1);
+
+        // Running a task that is not scheduled does not poll the future.
+        let task = queue.pop();
+        task.clone().run();
+        task.run();
+
+        assert_eq!(shared.lock().unwrap().polls, 2);
+    }
+
+    #[test]
+    fn wake_while_running() {
+        let (queue, shared) = spawn();
+        shared.lock().unwrap().wakes_while_running = 1;
+
+        queue.pop().run();
+
+        assert_eq!(queue.len(), 1);
+    }
+
+    #[test]
+    fn reschedule_after_notified_poll() {
+        let (queue, shared) = spawn();
+        shared.lock().unwrap().wakes_while_running = 3;
+
+        queue.pop().run();
+
+        // Waking up the task repeatedly while running schedules it once.
+        assert_eq!(queue.len(), 1);
+
+        shared.lock().unwrap().wakes_while_running = 0;
+        queue.pop().run();
+
+        assert_eq!(queue.len(), 0);
+        assert_eq!(shared.lock().unwrap().polls, 2);
+
+        wake(&shared);
+
+        assert_eq!(queue.len(), 1);
+    }
+
+    #[test]
+    fn complete_ignores_wakes() {
+        let (queue, shared) = spawn();
+        shared.lock().unwrap().ready = true;
+
+        let task = queue.pop();
+        task.clone().run();
+
+        assert!(task.is_complete());
+        assert_eq!(queue.completed(), 1);
+
+        wake(&shared);
+        task.clone().run();
+
+        assert_eq!(queue.len(), 0);
+        assert_eq!(queue.completed(), 1);
+        assert_eq!(shared.lock().unwrap().polls, 1);
+    }
+
+    #[test]
+    fn complete_while_notified() {
+        let (queue, shared) = spawn();
+        {
+            let mut shared = shared.lock().unwrap();
+            shared.ready = true;
+            shared.wakes_while_running = 1;
+        }
+
+        let task = queue.pop();
+        task.clone().run();
+
+        assert!(task.is_complete());
+        assert_eq!(queue.len(), 0);
+        assert_eq!(queue.completed(), 1);
+    }
+}
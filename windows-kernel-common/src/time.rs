@@ -0,0 +1,109 @@
+//! Conversions between [`Duration`] and the 100-nanosecond intervals that the kernel measures
+//! time in, and the encoding of the timeouts that the wait functions take.
+
+use core::time::Duration;
+
+/// The number of 100-nanosecond intervals in a second.
+pub const INTERVALS_PER_SECOND: u64 = 10_000_000;
+
+/// Converts the duration into a number of 100-nanosecond intervals, rounding down and saturating
+/// at `i64::MAX`.
+pub fn duration_to_intervals(duration: Duration) -> i64 {
+    (duration.as_nanos() / 100).min(i64::MAX as u128) as i64
+}
+
+/// Converts the duration into a number of 100-nanosecond intervals, rounding down. Returns `None`
+/// if the number does not fit into a `u64`.
+pub fn checked_duration_to_intervals(duration: Duration) -> Option<u64> {
+    u64::try_from(duration.as_nanos() / 100).ok()
+}
+
+/// Converts a number of 100-nanosecond intervals into a duration. As relative timeouts are
+/// negative, the sign of the number is ignored.
+pub fn intervals_to_duration(intervals: i64) -> Duration {
+    unsigned_intervals_to_duration(intervals.unsigned_abs())
+}
+
+/// Converts an unsigned number of 100-nanosecond intervals, e.g. the difference between two
+/// points in time, into a duration.
+pub fn unsigned_intervals_to_duration(intervals: u64) -> Duration {
+    Duration::new(
+        intervals / INTERVALS_PER_SECOND,
+        (intervals % INTERVALS_PER_SECOND) as u32 * 100,
+    )
+}
+
+/// Encodes a timeout that expires after the duration, as a negative number of 100-nanosecond
+/// intervals.
+pub fn relative_timeout(duration: Duration) -> i64 {
+    -duration_to_intervals(duration)
+}
+
+/// Encodes a timeout that expires once the system clock reaches the system time, given as the
+/// number of 100-nanosecond intervals since January 1, 1601 (UTC). The system time is clamped to
+/// `i64::MAX`, as negative numbers are relative timeouts.
+pub fn absolute_timeout(intervals: u64) -> i64 {
+    intervals.min(i64::MAX as u64) as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duration_to_intervals_rounds_down() {
+        assert_eq!(duration_to_intervals(Duration::ZERO), 0);
+        assert_eq!(duration_to_intervals(Duration::from_nanos(199)), 1);
+        assert_eq!(duration_to_intervals(Duration::from_millis(1)), 10_000);
+        assert_eq!(duration_to_intervals(Duration::from_secs(3)), 30_000_000);
+    }
+
+    #[test]
+    fn duration_to_intervals_saturates() {
+        assert_eq!(duration_to_intervals(Duration::MAX), i64::MAX);
+        assert_eq!(checked_duration_to_intervals(Duration::MAX), None);
+        assert_eq!(
+            checked_duration_to_intervals(Duration::from_secs(1)),
+            Some(INTERVALS_PER_SECOND),
+        );
+    }
+
+    #[test]
+    fn intervals_to_duration_ignores_sign() {
+        let duration = Duration::new(2, 500_000_000);
+
+        assert_eq!(intervals_to_duration(25_000_000), duration);
+        assert_eq!(intervals_to_duration(-25_000_000), duration);
+        assert_eq!(
+            intervals_to_duration(i64::MIN),
+            unsigned_intervals_to_duration(1 << 63),
+        );
+    }
+
+    #[test]
+    fn unsigned_intervals_beyond_i64() {
+        // Casting to `i64` first would wrap around, and yield a duration that is too short.
+        let intervals = (1 << 63) + INTERVALS_PER_SECOND;
+
+        assert_eq!(
+            unsigned_intervals_to_duration(intervals),
+            Duration::new(922_337_203_686, 477_580_800),
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let duration = Duration::new(12_345, 678_900);
+
+        assert_eq!(intervals_to_duration(duration_to_intervals(duration)), duration);
+    }
+
+    #[test]
+    fn timeouts() {
+        assert_eq!(relative_timeout(Duration::from_millis(5)), -50_000);
+        assert_eq!(relative_timeout(Duration::ZERO), 0);
+        assert_eq!(relative_timeout(Duration::MAX), -i64::MAX);
+        assert_eq!(absolute_timeout(116_444_736_000_000_000), 116_444_736_000_000_000);
+        assert_eq!(absolute_timeout(u64::MAX), i64::MAX);
+    }
+}
@@ -0,0 +1,103 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Error, LitStr, Path, Result};
+
+/// Derives `windows_kernel_rs::pod::Pod` for a struct. The struct must use `#[repr(C)]` or
+/// `#[repr(transparent)]`, all of its fields must implement `Pod` and it must not contain any
+/// padding, as padding bytes are uninitialized.
+///
+/// Crates that do not depend on `windows_kernel_rs`, such as user-mode crates, can name the crate
+/// that exports the `pod` module with `#[pod(crate = "winioctl")]`.
+#[proc_macro_derive(Pod, attributes(pod))]
+pub fn derive_pod(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match expand_pod(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand_pod(input: &DeriveInput) -> Result<TokenStream2> {
+    let name = &input.ident;
+
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => return Err(Error::new_spanned(name, "`Pod` can only be derived for structs")),
+    };
+
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "`Pod` cannot be derived for generic structs",
+        ));
+    }
+
+    if !has_stable_layout(&input.attrs)? {
+        return Err(Error::new_spanned(
+            name,
+            "`Pod` requires `#[repr(C)]` or `#[repr(transparent)]`",
+        ));
+    }
+
+    let krate = crate_path(&input.attrs)?;
+    let types: Vec<_> = fields.iter().map(|field| &field.ty).collect();
+
+    Ok(quote! {
+        const _: () = {
+            fn assert_pod<T: #krate::pod::Pod>() {}
+
+            fn assert_fields() {
+                #(assert_pod::<#types>();)*
+            }
+
+            assert!(
+                ::core::mem::size_of::<#name>() == 0 #(+ ::core::mem::size_of::<#types>())*,
+                "`Pod` types must not contain padding",
+            );
+        };
+
+        unsafe impl #krate::pod::Pod for #name {}
+    })
+}
+
+fn crate_path(attrs: &[Attribute]) -> Result<Path> {
+    let mut path = parse_quote!(::windows_kernel_rs);
+
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("pod")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("crate") {
+                path = meta.value()?.parse::<LitStr>()?.parse()?;
+                Ok(())
+            } else {
+                Err(meta.error("unsupported `pod` attribute, expected `crate`"))
+            }
+        })?;
+    }
+
+    Ok(path)
+}
+
+fn has_stable_layout(attrs: &[Attribute]) -> Result<bool> {
+    let mut stable = false;
+
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("repr")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("C") || meta.path.is_ident("transparent") {
+                stable = true;
+            }
+
+            // Skip the arguments of e.g. `align(8)` and `packed(2)`.
+            if meta.input.peek(syn::token::Paren) {
+                let content;
+                syn::parenthesized!(content in meta.input);
+                content.parse::<TokenStream2>()?;
+            }
+
+            Ok(())
+        })?;
+    }
+
+    Ok(stable)
+}
@@ -0,0 +1,17 @@
+use windows_kernel_derive::Pod;
+
+#[derive(Clone, Copy, Pod)]
+#[pod(crate = "windows_kernel_common")]
+struct Header {
+    count: u32,
+    flags: u32,
+}
+
+#[derive(Clone, Copy, Pod)]
+#[pod(crate = "windows_kernel_common")]
+#[repr(align(8))]
+struct Aligned {
+    value: u64,
+}
+
+fn main() {}
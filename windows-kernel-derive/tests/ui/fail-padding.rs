@@ -0,0 +1,11 @@
+use windows_kernel_derive::Pod;
+
+#[derive(Clone, Copy, Pod)]
+#[pod(crate = "windows_kernel_common")]
+#[repr(C)]
+struct Header {
+    tag: u8,
+    value: u32,
+}
+
+fn main() {}
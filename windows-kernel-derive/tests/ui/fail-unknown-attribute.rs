@@ -0,0 +1,11 @@
+use windows_kernel_derive::Pod;
+
+#[derive(Clone, Copy, Pod)]
+#[pod(path = "windows_kernel_common")]
+#[repr(C)]
+struct Header {
+    count: u32,
+    flags: u32,
+}
+
+fn main() {}
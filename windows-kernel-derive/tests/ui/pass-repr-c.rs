@@ -0,0 +1,31 @@
+use windows_kernel_derive::Pod;
+
+#[derive(Clone, Copy, Pod)]
+#[pod(crate = "windows_kernel_common")]
+#[repr(C)]
+struct Header {
+    count: u32,
+    flags: u32,
+    entries: [u16; 4],
+}
+
+#[derive(Clone, Copy, Pod)]
+#[pod(crate = "windows_kernel_common")]
+#[repr(transparent)]
+struct Handle(u64);
+
+#[derive(Clone, Copy, Pod)]
+#[pod(crate = "windows_kernel_common")]
+#[repr(C, packed)]
+struct Packed {
+    tag: u8,
+    value: u32,
+}
+
+fn assert_pod<T: windows_kernel_common::pod::Pod>() {}
+
+fn main() {
+    assert_pod::<Header>();
+    assert_pod::<Handle>();
+    assert_pod::<Packed>();
+}
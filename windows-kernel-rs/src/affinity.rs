@@ -26,6 +26,22 @@ pub fn get_cpu_count() -> u32 {
     }
 }
 
+/// Uses [`KeGetProcessorNumberFromIndex`] to convert the logical CPU number into the group and
+/// the number of the CPU within that group.
+pub(crate) fn get_processor_number(cpu_num: u32) -> Result<PROCESSOR_NUMBER, Error> {
+    let mut processor_num = PROCESSOR_NUMBER {
+        Group: 0,
+        Number: 0,
+        Reserved: 0,
+    };
+
+    unsafe {
+        KeGetProcessorNumberFromIndex(cpu_num, &mut processor_num)
+    }.into_result()?;
+
+    Ok(processor_num)
+}
+
 /// This is the callback used by [`run_on_each_cpu_parallel`] to run the closure on all CPUs.
 unsafe extern "C" fn broadcast_callback<F>(
     context: ULONG_PTR,
@@ -84,15 +100,7 @@ pub fn run_on_cpu<F>(
 where
     F: FnMut() -> Result<(), Error>,
 {
-    let mut processor_num = PROCESSOR_NUMBER {
-        Group: 0,
-        Number: 0,
-        Reserved: 0,
-    };
-
-    unsafe {
-        KeGetProcessorNumberFromIndex(cpu_num, &mut processor_num)
-    }.into_result()?;
+    let processor_num = get_processor_number(cpu_num)?;
 
     let mut previous = GROUP_AFFINITY {
         Mask: 0,
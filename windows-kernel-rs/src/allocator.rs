@@ -4,8 +4,8 @@
 //! [`ExAllocatePoolWithTag`].
 
 use core::alloc::{GlobalAlloc, Layout};
+use crate::sync::once::OnceLock;
 use crate::version::VersionInfo;
-use lazy_static::lazy_static;
 use windows_kernel_sys::base::_POOL_TYPE as POOL_TYPE;
 use windows_kernel_sys::ntoskrnl::{ExAllocatePoolWithTag, ExAllocatePool2, ExFreePool};
 
@@ -15,13 +15,26 @@ fn alloc_error(_: Layout) -> ! {
     loop {}
 }
 
-lazy_static! {
-    /// The version of Microsoft Windows that is currently running. This is used by
-    /// [`KernelAllocator`] to determine whether to use [`ExAllocatePool2`] or
-    /// [`ExAllocatePoolWithTag`].
-    static ref VERSION_INFO: VersionInfo = {
-        VersionInfo::query().unwrap()
-    };
+/// The version of Microsoft Windows that is currently running. This is used by
+/// [`KernelAllocator`] to determine whether to use [`ExAllocatePool2`] or
+/// [`ExAllocatePoolWithTag`], and is queried by [`init`] in `DriverEntry` at `PASSIVE_LEVEL`, as
+/// initializing it takes a lock that cannot be taken at the IRQLs that memory can be allocated at.
+static VERSION_INFO: OnceLock<VersionInfo> = OnceLock::new();
+
+/// Queries the version of Microsoft Windows that [`KernelAllocator`] uses to pick the allocation
+/// function. The [`kernel_module!`] macro calls this before any code of the driver runs. Drivers
+/// that define their own `DriverEntry` should call this first, otherwise [`KernelAllocator`]
+/// falls back to [`ExAllocatePoolWithTag`]. Can only be called at `PASSIVE_LEVEL`.
+///
+/// [`kernel_module!`]: crate::kernel_module
+pub fn init() {
+    crate::debug_assert_irql!(== PASSIVE_LEVEL);
+
+    if VERSION_INFO.get().is_none() {
+        if let Ok(version_info) = VersionInfo::query() {
+            let _ = VERSION_INFO.set(version_info);
+        }
+    }
 }
 
 /// Represents a kernel allocator that relies on the `ExAllocatePool` family of functions to
@@ -44,11 +57,16 @@ impl KernelAllocator {
 
 unsafe impl GlobalAlloc for KernelAllocator {
     /// Uses [`ExAllocatePool2`] on Microsoft Windows 10.0.19041 and later, and
-    /// [`ExAllocatePoolWithTag`] on older versions of Microsoft Windows to allocate memory.
+    /// [`ExAllocatePoolWithTag`] on older versions of Microsoft Windows to allocate memory. If the
+    /// version has not been queried through [`init`], [`ExAllocatePoolWithTag`] is used.
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        let use_ex_allocate_pool2 =
-            VERSION_INFO.major() > 10 ||
-            (VERSION_INFO.major() == 10 && VERSION_INFO.build_number() == 19041);
+        let use_ex_allocate_pool2 = match VERSION_INFO.get() {
+            Some(version_info) => {
+                version_info.major() > 10 ||
+                (version_info.major() == 10 && version_info.build_number() == 19041)
+            }
+            _ => false,
+        };
 
         let ptr = if use_ex_allocate_pool2 {
             ExAllocatePool2(
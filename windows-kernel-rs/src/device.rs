@@ -1,13 +1,31 @@
-use crate::error::Error;
-use crate::request::{IoControlRequest, IoRequest, ReadRequest, WriteRequest};
+use crate::driver::Driver;
+use crate::error::{Error, IntoResult};
+use crate::guid::Guid;
+use crate::request::{CompletionAction, CompletionRoutine, FlushRequest, InternalIoControlRequest, IoControlRequest};
+use crate::request::{IoRequest, PendingToken};
+use crate::request::{PnpRequest, PowerRequest, QueryInformationRequest, QueryVolumeInformationRequest};
+use crate::request::{ReadRequest, SetInformationRequest, ShutdownRequest, WriteRequest};
+use crate::string::create_unicode_string;
+use crate::sync::fast_mutex::FastMutex;
+use crate::sync::rundown::Rundown;
 use alloc::boxed::Box;
+use alloc::sync::Arc;
 use bitflags::bitflags;
-use windows_kernel_sys::base::STATUS_SUCCESS;
-use windows_kernel_sys::base::{DEVICE_OBJECT, IRP, NTSTATUS};
+use core::marker::PhantomData;
+use core::ptr::NonNull;
+use widestring::U16CString;
+use windows_kernel_sys::base::{STATUS_PENDING, STATUS_SUCCESS};
+use windows_kernel_sys::base::{DEVICE_OBJECT, FILE_OBJECT, IRP, NTSTATUS, UNICODE_STRING};
 use windows_kernel_sys::base::{
     IRP_MJ_CLEANUP, IRP_MJ_CLOSE, IRP_MJ_CREATE, IRP_MJ_DEVICE_CONTROL, IRP_MJ_READ, IRP_MJ_WRITE,
 };
-use windows_kernel_sys::ntoskrnl::{IoDeleteDevice, IoGetCurrentIrpStackLocation};
+use windows_kernel_sys::base::{
+    IRP_MJ_FLUSH_BUFFERS, IRP_MJ_INTERNAL_DEVICE_CONTROL, IRP_MJ_PNP, IRP_MJ_POWER,
+    IRP_MJ_QUERY_INFORMATION, IRP_MJ_QUERY_VOLUME_INFORMATION, IRP_MJ_SET_INFORMATION,
+    IRP_MJ_SHUTDOWN,
+};
+use windows_kernel_sys::ntoskrnl::{IoCreateDevice, IoCreateDeviceSecure, IoDeleteDevice, IoDetachDevice};
+use windows_kernel_sys::ntoskrnl::IoGetCurrentIrpStackLocation;
 
 #[derive(Copy, Clone, Debug)]
 pub enum Access {
@@ -26,7 +44,21 @@ impl Access {
 
 bitflags! {
     pub struct DeviceFlags: u32 {
+        const REMOVABLE_MEDIA = windows_kernel_sys::base::FILE_REMOVABLE_MEDIA;
+        const READ_ONLY_DEVICE = windows_kernel_sys::base::FILE_READ_ONLY_DEVICE;
+        const FLOPPY_DISKETTE = windows_kernel_sys::base::FILE_FLOPPY_DISKETTE;
+        const WRITE_ONCE_MEDIA = windows_kernel_sys::base::FILE_WRITE_ONCE_MEDIA;
+        const REMOTE_DEVICE = windows_kernel_sys::base::FILE_REMOTE_DEVICE;
+        const DEVICE_IS_MOUNTED = windows_kernel_sys::base::FILE_DEVICE_IS_MOUNTED;
+        const VIRTUAL_VOLUME = windows_kernel_sys::base::FILE_VIRTUAL_VOLUME;
+        const AUTOGENERATED_DEVICE_NAME = windows_kernel_sys::base::FILE_AUTOGENERATED_DEVICE_NAME;
         const SECURE_OPEN = windows_kernel_sys::base::FILE_DEVICE_SECURE_OPEN;
+        const CHARACTERISTIC_PNP_DEVICE = windows_kernel_sys::base::FILE_CHARACTERISTIC_PNP_DEVICE;
+        const CHARACTERISTIC_TS_DEVICE = windows_kernel_sys::base::FILE_CHARACTERISTIC_TS_DEVICE;
+        const CHARACTERISTIC_WEBDAV_DEVICE = windows_kernel_sys::base::FILE_CHARACTERISTIC_WEBDAV_DEVICE;
+        const CHARACTERISTIC_CSV = windows_kernel_sys::base::FILE_CHARACTERISTIC_CSV;
+        const ALLOW_APPCONTAINER_TRAVERSAL = windows_kernel_sys::base::FILE_DEVICE_ALLOW_APPCONTAINER_TRAVERSAL;
+        const PORTABLE_DEVICE = windows_kernel_sys::base::FILE_PORTABLE_DEVICE;
     }
 }
 
@@ -242,10 +274,224 @@ impl From<u32> for DeviceType {
     }
 }
 
+/// Only allows the kernel to open the device.
+pub const SDDL_DEVOBJ_KERNEL_ONLY: &str = "D:P";
+/// Allows the system to open the device.
+pub const SDDL_DEVOBJ_SYS_ALL: &str = "D:P(A;;GA;;;SY)";
+/// Allows the system and administrators to open the device.
+pub const SDDL_DEVOBJ_SYS_ALL_ADM_ALL: &str = "D:P(A;;GA;;;SY)(A;;GA;;;BA)";
+/// Allows the system to open the device, and administrators to open the device for reading.
+pub const SDDL_DEVOBJ_SYS_ALL_ADM_RX: &str = "D:P(A;;GA;;;SY)(A;;GRGX;;;BA)";
+/// Allows the system and administrators to open the device, and everyone to open the device for
+/// reading.
+pub const SDDL_DEVOBJ_SYS_ALL_ADM_RWX_WORLD_R: &str =
+    "D:P(A;;GA;;;SY)(A;;GRGWGX;;;BA)(A;;GR;;;WD)";
+/// Allows the system and administrators to open the device, everyone to open the device for
+/// reading and writing, and restricted code to open the device for reading.
+pub const SDDL_DEVOBJ_SYS_ALL_ADM_RWX_WORLD_RW_RES_R: &str =
+    "D:P(A;;GA;;;SY)(A;;GRGWGX;;;BA)(A;;GRGW;;;WD)(A;;GR;;;RC)";
+/// Allows everyone to open the device.
+pub const SDDL_DEVOBJ_SYS_ALL_ADM_RWX_WORLD_RWX_RES_RWX: &str =
+    "D:P(A;;GA;;;SY)(A;;GRGWGX;;;BA)(A;;GRGWGX;;;WD)(A;;GRGWGX;;;RC)";
+
+/// Builds a new device. Unlike [`Driver::create_device`], the builder allows the device to be
+/// created with a security descriptor and a device class through [`IoCreateDeviceSecure`].
+///
+/// ```ignore
+/// let device = DeviceBuilder::new(DeviceType::Unknown)
+///     .name("\\Device\\Example")
+///     .characteristics(DeviceFlags::SECURE_OPEN)
+///     .do_flags(DeviceDoFlags::DO_BUFFERED_IO)
+///     .security(SDDL_DEVOBJ_SYS_ALL_ADM_ALL)
+///     .build(&mut driver, MyDevice)?;
+/// ```
+///
+/// [`Driver::create_device`]: crate::driver::Driver::create_device
+pub struct DeviceBuilder<'a> {
+    name: DeviceName<'a>,
+    device_type: u32,
+    characteristics: u32,
+    do_flags: u32,
+    access: Access,
+    security: Option<(&'a str, Option<Guid>)>,
+}
+
+/// How the device is named. A device either has a name, an autogenerated name or no name, hence
+/// [`DeviceBuilder::name`] and [`DeviceBuilder::autogenerated_name`] replace each other.
+enum DeviceName<'a> {
+    Unnamed,
+    Named(&'a str),
+    Autogenerated,
+}
+
+impl<'a> DeviceBuilder<'a> {
+    pub fn new(device_type: DeviceType) -> Self {
+        Self::from_raw_parts(device_type.into(), 0)
+    }
+
+    /// Creates a builder for the raw device type and characteristics, e.g. as copied from another
+    /// device.
+    pub(crate) fn from_raw_parts(device_type: u32, characteristics: u32) -> Self {
+        let autogenerated = DeviceFlags::AUTOGENERATED_DEVICE_NAME.bits();
+
+        Self {
+            name: match characteristics & autogenerated {
+                0 => DeviceName::Unnamed,
+                _ => DeviceName::Autogenerated,
+            },
+            device_type,
+            characteristics: characteristics & !autogenerated,
+            do_flags: 0,
+            access: Access::NonExclusive,
+            security: None,
+        }
+    }
+
+    /// Sets the name of the device, e.g. `\Device\Example`. Without a name, an unnamed
+    /// device is created, unless [`autogenerated_name`] is used, which this replaces.
+    ///
+    /// [`autogenerated_name`]: DeviceBuilder::autogenerated_name
+    pub fn name(mut self, name: &'a str) -> Self {
+        self.name = DeviceName::Named(name);
+        self
+    }
+
+    /// Lets the I/O manager generate a name for the device. This is typically used for physical
+    /// device objects. This replaces any name set through [`name`].
+    ///
+    /// [`name`]: DeviceBuilder::name
+    pub fn autogenerated_name(mut self) -> Self {
+        self.name = DeviceName::Autogenerated;
+        self
+    }
+
+    /// Adds the given characteristics. [`DeviceFlags::AUTOGENERATED_DEVICE_NAME`] is ignored, use
+    /// [`autogenerated_name`] instead.
+    ///
+    /// [`autogenerated_name`]: DeviceBuilder::autogenerated_name
+    pub fn characteristics(mut self, flags: DeviceFlags) -> Self {
+        self.characteristics |= (flags - DeviceFlags::AUTOGENERATED_DEVICE_NAME).bits();
+        self
+    }
+
+    pub fn do_flags(mut self, flags: DeviceDoFlags) -> Self {
+        self.do_flags |= flags.bits();
+        self
+    }
+
+    pub fn access(mut self, access: Access) -> Self {
+        self.access = access;
+        self
+    }
+
+    /// Sets the default security descriptor of the device as an SDDL string, e.g.
+    /// [`SDDL_DEVOBJ_SYS_ALL_ADM_ALL`] to only allow the system and administrators to open the
+    /// device.
+    pub fn security(mut self, sddl: &'a str) -> Self {
+        self.security = Some((sddl, None));
+        self
+    }
+
+    /// Sets the default security descriptor of the device as an SDDL string, like [`security`],
+    /// along with the device class of the device. Any security settings stored in the registry
+    /// for the device class override the default security descriptor.
+    ///
+    /// [`security`]: DeviceBuilder::security
+    pub fn security_with_class(mut self, sddl: &'a str, class_guid: Guid) -> Self {
+        self.security = Some((sddl, Some(class_guid)));
+        self
+    }
+
+    pub fn build<T>(self, driver: &mut Driver, data: T) -> Result<Device, Error>
+    where
+        T: DeviceOperations
+    {
+        // Convert the name to UTF-16 and then create a UNICODE_STRING.
+        let (name, characteristics) = match self.name {
+            DeviceName::Unnamed => (None, self.characteristics),
+            DeviceName::Named(name) => {
+                (Some(U16CString::from_str(name).unwrap()), self.characteristics)
+            }
+            DeviceName::Autogenerated => {
+                let autogenerated = DeviceFlags::AUTOGENERATED_DEVICE_NAME.bits();
+
+                (None, self.characteristics | autogenerated)
+            }
+        };
+        let mut name = name.as_ref().map(|name| create_unicode_string(name.as_slice()));
+
+        let name_ptr = name.as_mut()
+            .map(|name| name as *mut UNICODE_STRING)
+            .unwrap_or(core::ptr::null_mut());
+
+        // Box the data.
+        let data = Box::new(data);
+
+        // Create the device.
+        let mut device = core::ptr::null_mut();
+
+        match self.security {
+            Some((sddl, class_guid)) => {
+                let sddl = U16CString::from_str(sddl).unwrap();
+                let sddl = create_unicode_string(sddl.as_slice());
+
+                let class_guid = class_guid
+                    .as_ref()
+                    .map(|guid| guid.as_raw())
+                    .unwrap_or(core::ptr::null());
+
+                unsafe {
+                    IoCreateDeviceSecure(
+                        driver.raw,
+                        core::mem::size_of::<DeviceExtension>() as u32,
+                        name_ptr,
+                        self.device_type,
+                        characteristics,
+                        self.access.is_exclusive() as _,
+                        &sddl,
+                        class_guid,
+                        &mut device,
+                    )
+                }.into_result()?;
+            }
+            _ => {
+                unsafe {
+                    IoCreateDevice(
+                        driver.raw,
+                        core::mem::size_of::<DeviceExtension>() as u32,
+                        name_ptr,
+                        self.device_type,
+                        characteristics,
+                        self.access.is_exclusive() as _,
+                        &mut device,
+                    )
+                }.into_result()?;
+            }
+        }
+
+        let device = unsafe { Device::from_raw(device) };
+
+        // Store the boxed data and vtable.
+        let extension = device.extension_mut();
+        extension.device_type = self.device_type.into();
+        extension.vtable = &DeviceOperationsVtable::<T>::VTABLE;
+        extension.data = Box::into_raw(data) as *mut cty::c_void;
+        extension.lower_device = core::ptr::null_mut();
+        extension.rundown = Rundown::new();
+
+        unsafe {
+            (*device.as_raw_mut()).Flags |= self.do_flags;
+        }
+
+        Ok(device)
+    }
+}
+
 #[repr(C)]
 pub struct device_operations {
     dispatch: Option<extern "C" fn(*mut DEVICE_OBJECT, *mut IRP, u8) -> NTSTATUS>,
     release: Option<extern "C" fn(*mut DEVICE_OBJECT)>,
+    release_file: Option<extern "C" fn(*mut cty::c_void)>,
 }
 
 pub struct Device {
@@ -284,16 +530,53 @@ impl Device {
         self.extension().device_type
     }
 
+    /// Returns the device that this device is attached to, if this device has been attached to a
+    /// device stack using [`Driver::attach_device`].
+    ///
+    /// [`Driver::attach_device`]: crate::driver::Driver::attach_device
+    pub fn lower_device(&self) -> Option<LowerDevice<'_>> {
+        NonNull::new(self.extension().lower_device).map(|raw| LowerDevice {
+            raw,
+            _marker: PhantomData,
+        })
+    }
+
     pub(crate) fn vtable(&self) -> &device_operations {
         unsafe { &*(self.extension().vtable as *const _) }
     }
 
+    /// Returns the data of the device. `T` must be the type that the device was created with.
     pub fn data<T: DeviceOperations>(&self) -> &T {
         unsafe { &*(self.extension().data as *const T) }
     }
 
-    pub fn data_mut<T: DeviceOperations>(&self) -> &mut T {
-        unsafe { &mut *(self.extension().data as *mut T) }
+}
+
+/// The device that a [`Device`] is attached to, as returned by [`Device::lower_device`]. The
+/// lower device remains valid for as long as the device stays attached, i.e. for as long as the
+/// [`Device`] is borrowed.
+#[derive(Clone, Copy)]
+pub struct LowerDevice<'a> {
+    raw: NonNull<DEVICE_OBJECT>,
+    _marker: PhantomData<&'a Device>,
+}
+
+unsafe impl Send for LowerDevice<'_> {}
+unsafe impl Sync for LowerDevice<'_> {}
+
+impl<'a> LowerDevice<'a> {
+    pub unsafe fn as_raw(&self) -> *const DEVICE_OBJECT {
+        self.raw.as_ptr() as *const _
+    }
+
+    pub unsafe fn as_raw_mut(&self) -> *mut DEVICE_OBJECT {
+        self.raw.as_ptr()
+    }
+
+    /// Returns the number of stack locations that requests sent to the lower device need, e.g.
+    /// to allocate a request through `IoAllocateIrp`.
+    pub fn stack_size(&self) -> i8 {
+        unsafe { (*self.raw.as_ptr()).StackSize }
     }
 }
 
@@ -304,6 +587,15 @@ impl Drop for Device {
         }
 
         unsafe {
+            // Fail any new requests and wait for the requests that are being dispatched, before
+            // detaching from the lower device and releasing the data of the device, as the
+            // dispatch routines may still forward requests to the lower device.
+            self.extension().rundown.wait();
+
+            if let Some(lower_device) = self.lower_device() {
+                IoDetachDevice(lower_device.as_raw_mut());
+            }
+
             if let Some(release) = self.vtable().release {
                 release(self.raw);
             }
@@ -316,75 +608,525 @@ impl Drop for Device {
 pub struct RequestError(pub Error, pub IoRequest);
 
 pub enum Completion {
+    /// Completes the request with the given size.
     Complete(u32, IoRequest),
+    /// The request has been turned into a [`PendingRequest`] that will be completed later on.
+    /// The [`PendingToken`] proves that the request has been marked as pending.
+    ///
+    /// [`PendingRequest`]: crate::request::PendingRequest
+    Pending(PendingToken),
+    /// Passes the request on to the lower device using `IoSkipCurrentIrpStackLocation`.
+    Forward(IoRequest),
+    /// Passes the request on to the lower device using `IoCopyCurrentIrpStackLocationToNext`.
+    ForwardCopy(IoRequest),
+    /// Passes the request on to the lower device using `IoCopyCurrentIrpStackLocationToNext`,
+    /// and calls the completion routine once the lower device has completed the request. The
+    /// request is marked as pending, such that the completion routine can stop the completion of
+    /// the request through [`CompletionAction::MoreProcessingRequired`].
+    ForwardWith(IoRequest, CompletionRoutine),
+}
+
+impl Completion {
+    /// Passes the request on to the lower device, and calls the given closure with the result
+    /// once the lower device has completed the request. The [`CompletionAction`] returned by the
+    /// closure decides how the completion of the request proceeds.
+    ///
+    /// ```ignore
+    /// Ok(Completion::forward_with(request, |request, result| match result {
+    ///     Err(Error::NOT_SUPPORTED) => CompletionAction::ContinueWith(Ok(0), request),
+    ///     _ => CompletionAction::Continue(request),
+    /// }))
+    /// ```
+    pub fn forward_with<F>(request: IoRequest, f: F) -> Self
+    where
+        F: FnOnce(IoRequest, Result<u32, Error>) -> CompletionAction + Send + 'static,
+    {
+        Completion::ForwardWith(request, Box::new(f))
+    }
+}
+
+/// Completes the request, unless the device is attached to a device stack, in which case the
+/// request is passed on to the lower device.
+fn complete_or_forward(device: &Device, request: IoRequest) -> Result<Completion, RequestError> {
+    match device.lower_device() {
+        Some(_) => Ok(Completion::Forward(request)),
+        _ => Ok(Completion::Complete(0, request)),
+    }
 }
 
-pub trait DeviceOperations: Sync + Sized {
-    fn create(&mut self, _device: &Device, request: IoRequest) -> Result<Completion, RequestError> {
-        Ok(Completion::Complete(0, request))
+/// Completes the request with the status it already has, unless the device is attached to a
+/// device stack, in which case the request is passed on to the lower device. This is used for
+/// Plug and Play and power requests, which the I/O manager initializes with
+/// `STATUS_NOT_SUPPORTED`, and which a driver must not fail unless it handles them.
+fn complete_unchanged_or_forward(
+    device: &Device,
+    request: IoRequest,
+) -> Result<Completion, RequestError> {
+    if device.lower_device().is_some() {
+        return Ok(Completion::Forward(request));
+    }
+
+    match request.status() {
+        Ok(size) => Ok(Completion::Complete(size, request)),
+        Err(error) => Err(RequestError(error, request)),
     }
+}
 
-    fn close(&mut self, _device: &Device, request: IoRequest) -> Result<Completion, RequestError> {
-        Ok(Completion::Complete(0, request))
+/// Fails the request with the given error, unless the device is attached to a device stack, in
+/// which case the request is passed on to the lower device.
+fn fail_or_forward(
+    device: &Device,
+    request: IoRequest,
+    error: Error,
+) -> Result<Completion, RequestError> {
+    match device.lower_device() {
+        Some(_) => Ok(Completion::Forward(request)),
+        _ => Err(RequestError(error, request)),
+    }
+}
+
+/// Defines the handlers of [`DeviceOperations`] and [`SerializedDeviceOperations`] from a single
+/// list, along with the implementation of [`DeviceOperations`] for [`Serialized`] that calls the
+/// handlers of the wrapped data while holding its lock.
+macro_rules! device_operations {
+    (
+        $(
+            $(#[$attr:meta])*
+            fn $name:ident($($param:ident: $param_ty:ty),* $(,)?) $default:block
+        )*
+    ) => {
+        /// The operations of a device.
+        ///
+        /// Requests for the same device can be dispatched on several CPUs at once, hence the
+        /// operations take `&self` and any state that is modified has to be protected through
+        /// interior mutability, e.g. using the locks in [`crate::sync`]. Devices that would rather
+        /// handle one request at a time can implement [`SerializedDeviceOperations`] instead and
+        /// wrap their data in [`Serialized`].
+        pub trait DeviceOperations: Send + Sync + Sized {
+            /// The state that is kept for every handle to the device. The file context is created
+            /// when the device is opened and is passed to [`create`] to initialize it. It is
+            /// stored in the `FsContext` of the file object, passed to the other operations, and
+            /// dropped once the handle has been closed, or once the create request has failed.
+            ///
+            /// As the file context is shared with any request that is dispatched while [`create`]
+            /// is still running, e.g. after the create request has been pended and completed from
+            /// another thread, it is only ever borrowed immutably.
+            ///
+            /// [`create`]: DeviceOperations::create
+            type FileContext: Default + Send + Sync = ();
+
+            $(
+                $(#[$attr])*
+                fn $name(&self, $($param: $param_ty),*) -> Result<Completion, RequestError>
+                    $default
+            )*
+        }
+
+        /// The operations of a device that handles one request at a time, see [`Serialized`].
+        /// Except for taking `&mut self`, the operations are the same as those of
+        /// [`DeviceOperations`].
+        pub trait SerializedDeviceOperations: Send + Sized {
+            /// The state that is kept for every handle to the device, see
+            /// [`DeviceOperations::FileContext`].
+            type FileContext: Default + Send + Sync = ();
+
+            $(
+                $(#[$attr])*
+                fn $name(&mut self, $($param: $param_ty),*) -> Result<Completion, RequestError>
+                    $default
+            )*
+        }
+
+        impl<T: SerializedDeviceOperations> DeviceOperations for Serialized<T> {
+            type FileContext = T::FileContext;
+
+            $(
+                fn $name(&self, $($param: $param_ty),*) -> Result<Completion, RequestError> {
+                    self.with(|data| data.$name($($param),*))
+                }
+            )*
+        }
+    };
+}
+
+device_operations! {
+    fn create(
+        device: &Device,
+        _file: &Self::FileContext,
+        request: IoRequest,
+    ) {
+        complete_or_forward(device, request)
+    }
+
+    fn close(
+        device: &Device,
+        _file: &Self::FileContext,
+        request: IoRequest,
+    ) {
+        complete_or_forward(device, request)
     }
 
     fn cleanup(
-        &mut self,
-        _device: &Device,
+        device: &Device,
+        _file: &Self::FileContext,
         request: IoRequest,
-    ) -> Result<Completion, RequestError> {
-        Ok(Completion::Complete(0, request))
+    ) {
+        complete_or_forward(device, request)
     }
 
-    fn read(&mut self, _device: &Device, request: ReadRequest) -> Result<Completion, RequestError> {
-        Ok(Completion::Complete(0, request.into()))
+    fn read(
+        device: &Device,
+        _file: &Self::FileContext,
+        request: ReadRequest,
+    ) {
+        complete_or_forward(device, request.into())
     }
 
     fn write(
-        &mut self,
-        _device: &Device,
+        device: &Device,
+        _file: &Self::FileContext,
         request: WriteRequest,
-    ) -> Result<Completion, RequestError> {
-        Ok(Completion::Complete(0, request.into()))
+    ) {
+        complete_or_forward(device, request.into())
     }
 
     fn ioctl(
-        &mut self,
-        _device: &Device,
+        device: &Device,
+        _file: &Self::FileContext,
         request: IoControlRequest,
-    ) -> Result<Completion, RequestError> {
-        Ok(Completion::Complete(0, request.into()))
+    ) {
+        complete_or_forward(device, request.into())
+    }
+
+    /// Handles I/O control codes sent by other drivers. These requests do not necessarily have a
+    /// file object, hence there is no file context.
+    fn internal_ioctl(
+        device: &Device,
+        request: InternalIoControlRequest,
+    ) {
+        fail_or_forward(device, request.into(), Error::INVALID_DEVICE_REQUEST)
+    }
+
+    fn flush(
+        device: &Device,
+        _file: &Self::FileContext,
+        request: FlushRequest,
+    ) {
+        complete_or_forward(device, request.into())
+    }
+
+    fn query_information(
+        device: &Device,
+        _file: &Self::FileContext,
+        request: QueryInformationRequest,
+    ) {
+        fail_or_forward(device, request.into(), Error::INVALID_DEVICE_REQUEST)
+    }
+
+    fn set_information(
+        device: &Device,
+        _file: &Self::FileContext,
+        request: SetInformationRequest,
+    ) {
+        fail_or_forward(device, request.into(), Error::INVALID_DEVICE_REQUEST)
+    }
+
+    fn query_volume_information(
+        device: &Device,
+        _file: &Self::FileContext,
+        request: QueryVolumeInformationRequest,
+    ) {
+        fail_or_forward(device, request.into(), Error::INVALID_DEVICE_REQUEST)
+    }
+
+    /// Handles the shutdown notification. This is only sent to devices that have been registered
+    /// through `IoRegisterShutdownNotification` and there is no file context.
+    fn shutdown(
+        device: &Device,
+        request: ShutdownRequest,
+    ) {
+        complete_or_forward(device, request.into())
+    }
+
+    /// Handles Plug and Play requests. By default these are passed down the device stack, or
+    /// completed with the status that is already in the request, which the I/O manager
+    /// initializes to [`Error::NOT_SUPPORTED`].
+    fn pnp(
+        device: &Device,
+        request: PnpRequest,
+    ) {
+        complete_unchanged_or_forward(device, request.into())
+    }
+
+    /// Handles power requests. By default these are passed down the device stack, or completed
+    /// with the status that is already in the request. Power requests are passed on through
+    /// `PoStartNextPowerIrp` and `PoCallDriver`, and `PoStartNextPowerIrp` is also called upon
+    /// completion.
+    fn power(
+        device: &Device,
+        request: PowerRequest,
+    ) {
+        complete_unchanged_or_forward(device, request.into())
     }
 }
 
+/// Wraps the data of a device that implements [`SerializedDeviceOperations`], such that the
+/// requests for the device are handled one at a time while holding a fast mutex. As acquiring a
+/// fast mutex raises the IRQL to `APC_LEVEL`, the operations run at `APC_LEVEL`. For the same
+/// reason, devices that receive requests at `DISPATCH_LEVEL`, e.g. power requests for devices that
+/// are not `DO_POWER_PAGABLE`, cannot use this.
+///
+/// ```ignore
+/// let device = driver.create_device(
+///     "\\Device\\Example",
+///     DeviceType::Unknown,
+///     DeviceFlags::SECURE_OPEN,
+///     DeviceDoFlags::DO_BUFFERED_IO,
+///     Access::NonExclusive,
+///     Serialized::new(MyDevice { value: 0 }),
+/// )?;
+/// ```
+pub struct Serialized<T> {
+    inner: FastMutex<T>,
+}
+
+impl<T> Serialized<T> {
+    pub fn new(data: T) -> Self {
+        Self {
+            inner: FastMutex::new(data),
+        }
+    }
+
+    /// Calls the closure with exclusive access to the data.
+    pub fn with<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        f(&mut self.inner.acquire())
+    }
+
+    /// Consumes the wrapper, returning the underlying data.
+    pub fn into_inner(self) -> T {
+        self.inner.into_inner()
+    }
+}
+
+impl<T> From<T> for Serialized<T> {
+    fn from(data: T) -> Self {
+        Self::new(data)
+    }
+}
+
+/// Returns the file object of the request, unless the device is attached to a device stack. In
+/// that case the `FsContext` of the file object belongs to the file system or the lower device.
+fn file_object(device: &Device, request: &IoRequest) -> *mut FILE_OBJECT {
+    match device.lower_device() {
+        Some(_) => core::ptr::null_mut(),
+        _ => request.stack_location().FileObject,
+    }
+}
+
+/// Looks up the file context that [`dispatch_create`] stored in the file object of the request.
+/// The file context is borrowed for as long as the device is borrowed by the dispatch routine,
+/// i.e. for the duration of the dispatch of the request, during which the file object holds on
+/// to the file context.
+///
+/// Returns `None` for devices that are attached to a device stack, see [`file_object`].
+unsafe fn file_context<'a, T: DeviceOperations>(
+    device: &'a Device,
+    request: &IoRequest,
+) -> Option<&'a T::FileContext> {
+    let file_object = file_object(device, request);
+
+    if file_object.is_null() || (*file_object).FsContext.is_null() {
+        return None;
+    }
+
+    Some(&*((*file_object).FsContext as *const T::FileContext))
+}
+
+/// Calls the given closure with the file context of the request. [`Driver::attach_device`]
+/// restricts devices that are attached to a device stack to `FileContext = ()`, hence these get a
+/// default file context instead.
+///
+/// [`Driver::attach_device`]: crate::driver::Driver::attach_device
+fn with_file_context<T, F>(
+    device: &Device,
+    request: IoRequest,
+    f: F,
+) -> Result<Completion, RequestError>
+where
+    T: DeviceOperations,
+    F: FnOnce(&T::FileContext, IoRequest) -> Result<Completion, RequestError>,
+{
+    if device.lower_device().is_some() {
+        return f(&T::FileContext::default(), request);
+    }
+
+    match unsafe { file_context::<T>(device, &request) } {
+        Some(file) => f(file, request),
+        _ => Err(RequestError(Error::INVALID_DEVICE_REQUEST, request)),
+    }
+}
+
+fn dispatch_create<T: DeviceOperations>(
+    data: &T,
+    device: &Device,
+    request: IoRequest,
+) -> Result<Completion, RequestError> {
+    let file_object = file_object(device, &request);
+
+    if file_object.is_null() {
+        return data.create(device, &T::FileContext::default(), request);
+    }
+
+    // Store the file context before calling `create`, such that the file context is available in
+    // case the request gets completed from another thread. The file object holds one reference
+    // and `create` holds another, as the file context is released as soon as the request fails,
+    // which may happen on another thread while `create` is still running.
+    let file = Arc::new(T::FileContext::default());
+
+    unsafe {
+        (*file_object).FsContext = Arc::into_raw(file.clone()) as _;
+    }
+
+    data.create(device, &file, request)
+}
+
+/// Releases the file context of a create request that failed. The I/O manager does not send
+/// `IRP_MJ_CLOSE` for a file object that failed to open, hence [`IoRequest::complete`] calls this
+/// for any create request that it completes with an error, including pended requests.
+pub(crate) unsafe fn release_file_context(request: &IoRequest) {
+    let device = Device::from_raw(request.stack_location().DeviceObject);
+    let file_object = file_object(&device, request);
+
+    if !file_object.is_null() && !(*file_object).FsContext.is_null() {
+        if let Some(release_file) = device.vtable().release_file {
+            release_file(core::mem::replace(&mut (*file_object).FsContext, core::ptr::null_mut()));
+        }
+    }
+
+    device.into_raw();
+}
+
+fn dispatch_close<T: DeviceOperations>(
+    data: &T,
+    device: &Device,
+    request: IoRequest,
+) -> Result<Completion, RequestError> {
+    let file_object = file_object(device, &request);
+
+    let result = with_file_context::<T, _>(device, request, |file, request| {
+        data.close(device, file, request)
+    });
+
+    if !file_object.is_null() && !unsafe { (*file_object).FsContext }.is_null() {
+        unsafe {
+            let file = core::mem::replace(&mut (*file_object).FsContext, core::ptr::null_mut());
+            release_file_context_callback::<T>(file);
+        }
+    }
+
+    result
+}
+
 extern "C" fn dispatch_callback<T: DeviceOperations>(
     device: *mut DEVICE_OBJECT,
     irp: *mut IRP,
     major: u8,
 ) -> NTSTATUS {
     let device = unsafe { Device::from_raw(device) };
-    let data: &mut T = device.data_mut();
+    let data: &T = device.data();
     let request = unsafe { IoRequest::from_raw(irp) };
 
     let result = match major as _ {
-        IRP_MJ_CREATE => data.create(&device, request),
-        IRP_MJ_CLOSE => data.close(&device, request),
-        IRP_MJ_CLEANUP => data.cleanup(&device, request),
+        IRP_MJ_CREATE => dispatch_create(data, &device, request),
+        IRP_MJ_CLOSE => dispatch_close(data, &device, request),
+        // These requests do not necessarily originate from a handle to the device.
+        IRP_MJ_INTERNAL_DEVICE_CONTROL => {
+            data.internal_ioctl(&device, InternalIoControlRequest { inner: request })
+        }
+        IRP_MJ_SHUTDOWN => data.shutdown(&device, ShutdownRequest { inner: request }),
+        IRP_MJ_PNP => data.pnp(&device, PnpRequest { inner: request }),
+        IRP_MJ_POWER => data.power(&device, PowerRequest { inner: request }),
+        _ => with_file_context::<T, _>(&device, request, |file, request| {
+            dispatch_request(data, &device, file, request, major)
+        }),
+    };
+
+    let lower_device = device.extension().lower_device;
+
+    let device = device.into_raw();
+
+    match result {
+        Ok(Completion::Complete(size, request)) => {
+            request.complete(Ok(size));
+            STATUS_SUCCESS
+        }
+        Ok(Completion::Pending(_)) => STATUS_PENDING,
+        Ok(Completion::Forward(request)) => {
+            forward_request(lower_device, request, |request| request.skip_current_stack_location())
+        }
+        Ok(Completion::ForwardCopy(request)) => {
+            forward_request(lower_device, request, |request| {
+                request.copy_current_stack_location_to_next()
+            })
+        }
+        Ok(Completion::ForwardWith(request, routine)) => {
+            // The completion routine may stop the completion of the request, hence the request
+            // has to be marked as pending up front, regardless of what the lower device returns.
+            request.mark_pending();
+
+            if lower_device.is_null() {
+                request.complete(Err(Error::INVALID_DEVICE_REQUEST));
+                return STATUS_PENDING;
+            }
+
+            request.copy_current_stack_location_to_next();
+
+            match request.set_completion_routine(device, routine) {
+                Ok(()) => {
+                    let _ = forward_request(lower_device, request, |_| ());
+                }
+                Err(e) => request.complete(Err(e)),
+            }
+
+            STATUS_PENDING
+        }
+        Err(RequestError(e, request)) => {
+            let status = e.to_ntstatus();
+            request.complete(Err(e));
+            status
+        }
+    }
+}
+
+fn dispatch_request<T: DeviceOperations>(
+    data: &T,
+    device: &Device,
+    file: &T::FileContext,
+    request: IoRequest,
+    major: u8,
+) -> Result<Completion, RequestError> {
+    match major as _ {
+        IRP_MJ_CLEANUP => data.cleanup(device, file, request),
         IRP_MJ_READ => {
             let read_request = ReadRequest { inner: request };
 
-            data.read(&device, read_request)
+            data.read(device, file, read_request)
         }
         IRP_MJ_WRITE => {
             let write_request = WriteRequest { inner: request };
 
-            data.write(&device, write_request)
+            data.write(device, file, write_request)
         }
         IRP_MJ_DEVICE_CONTROL => {
             let control_request = IoControlRequest { inner: request };
 
             if device.device_type() == control_request.control_code().device_type() {
-                data.ioctl(&device, control_request)
+                data.ioctl(device, file, control_request)
+            } else if device.lower_device().is_some() {
+                Ok(Completion::Forward(control_request.into()))
             } else {
                 Err(RequestError(
                     Error::INVALID_PARAMETER,
@@ -392,24 +1134,49 @@ extern "C" fn dispatch_callback<T: DeviceOperations>(
                 ))
             }
         }
-        _ => Err(RequestError(Error::INVALID_PARAMETER, request)),
-    };
-
-    device.into_raw();
-
-    match result {
-        Ok(Completion::Complete(size, request)) => {
-            request.complete(Ok(size));
-            STATUS_SUCCESS
+        IRP_MJ_FLUSH_BUFFERS => data.flush(device, file, FlushRequest { inner: request }),
+        IRP_MJ_QUERY_INFORMATION => {
+            data.query_information(device, file, QueryInformationRequest { inner: request })
         }
-        Err(RequestError(e, request)) => {
-            let status = e.to_ntstatus();
-            request.complete(Err(e));
-            status
+        IRP_MJ_SET_INFORMATION => {
+            data.set_information(device, file, SetInformationRequest { inner: request })
+        }
+        IRP_MJ_QUERY_VOLUME_INFORMATION => {
+            let request = QueryVolumeInformationRequest { inner: request };
+
+            data.query_volume_information(device, file, request)
         }
+        _ => fail_or_forward(device, request, Error::INVALID_PARAMETER),
     }
 }
 
+fn forward_request<F>(
+    lower_device: *mut DEVICE_OBJECT,
+    request: IoRequest,
+    prepare: F,
+) -> NTSTATUS
+where
+    F: FnOnce(&IoRequest),
+{
+    if lower_device.is_null() {
+        let e = Error::INVALID_DEVICE_REQUEST;
+        let status = e.to_ntstatus();
+        request.complete(Err(e));
+        return status;
+    }
+
+    // Power requests have to be passed on through `PoStartNextPowerIrp` and `PoCallDriver`.
+    if request.major() as u32 == IRP_MJ_POWER {
+        request.start_next_power_irp();
+        prepare(&request);
+
+        return request.call_power_driver(lower_device);
+    }
+
+    prepare(&request);
+    request.call_driver(lower_device)
+}
+
 extern "C" fn release_callback<T: DeviceOperations>(device: *mut DEVICE_OBJECT) {
     unsafe {
         let extension = (*device).DeviceExtension as *mut DeviceExtension;
@@ -419,12 +1186,19 @@ extern "C" fn release_callback<T: DeviceOperations>(device: *mut DEVICE_OBJECT)
     }
 }
 
+extern "C" fn release_file_context_callback<T: DeviceOperations>(file: *mut cty::c_void) {
+    unsafe {
+        let _ = Arc::from_raw(file as *const T::FileContext);
+    }
+}
+
 pub(crate) struct DeviceOperationsVtable<T>(core::marker::PhantomData<T>);
 
 impl<T: DeviceOperations> DeviceOperationsVtable<T> {
     pub(crate) const VTABLE: device_operations = device_operations {
         dispatch: Some(dispatch_callback::<T>),
         release: Some(release_callback::<T>),
+        release_file: Some(release_file_context_callback::<T>),
     };
 }
 
@@ -433,11 +1207,30 @@ pub struct DeviceExtension {
     pub(crate) vtable: *const device_operations,
     pub(crate) data: *mut cty::c_void,
     pub(crate) device_type: DeviceType,
+    pub(crate) lower_device: *mut DEVICE_OBJECT,
+    pub(crate) rundown: Rundown,
 }
 
 pub extern "C" fn dispatch_device(device: *mut DEVICE_OBJECT, irp: *mut IRP) -> NTSTATUS {
     let stack_location = unsafe { &*IoGetCurrentIrpStackLocation(irp) };
     let device = unsafe { Device::from_raw(device) };
+
+    // Hold run-time protection while dispatching the request, such that the device cannot be
+    // deleted underneath us.
+    let extension = unsafe { &*((*device.raw).DeviceExtension as *const DeviceExtension) };
+
+    let _guard = match extension.rundown.acquire() {
+        Some(guard) => guard,
+        _ => {
+            device.into_raw();
+
+            let e = Error::DELETE_PENDING;
+            let status = e.to_ntstatus();
+            unsafe { IoRequest::from_raw(irp) }.complete(Err(e));
+            return status;
+        }
+    };
+
     let vtable = device.vtable();
 
     match vtable.dispatch {
@@ -0,0 +1,82 @@
+use crate::error::{Error, IntoResult};
+use crate::guid::Guid;
+use crate::string::create_unicode_string;
+use alloc::string::String;
+use widestring::U16CString;
+use windows_kernel_sys::base::{DEVICE_OBJECT, UNICODE_STRING};
+use windows_kernel_sys::ntoskrnl::{IoRegisterDeviceInterface, IoSetDeviceInterfaceState, RtlFreeUnicodeString};
+
+/// A device interface that has been registered through [`IoRegisterDeviceInterface`] and enabled
+/// through [`IoSetDeviceInterfaceState`]. User mode applications can find the device by looking
+/// up the interface class, rather than relying on a fixed symbolic link. The interface is disabled
+/// when it is dropped.
+pub struct DeviceInterface {
+    name: U16CString,
+}
+
+impl DeviceInterface {
+    /// Registers and enables a device interface of the given interface class for the physical
+    /// device object, e.g. as passed to the `AddDevice` routine of the driver. The devices created
+    /// through [`DeviceBuilder`] are not physical device objects, hence they cannot be used. The
+    /// optional reference string is appended to the symbolic link name and can be used to
+    /// distinguish multiple interfaces of the same class. Returns [`Error::INVALID_PARAMETER`] if
+    /// the reference string contains a nul character.
+    ///
+    /// [`DeviceBuilder`]: crate::device::DeviceBuilder
+    pub unsafe fn from_raw_device(
+        physical_device: *mut DEVICE_OBJECT,
+        class_guid: &Guid,
+        reference: Option<&str>,
+    ) -> Result<Self, Error> {
+        // Convert the reference string to UTF-16 and then create a UNICODE_STRING.
+        let reference = reference
+            .map(U16CString::from_str)
+            .transpose()
+            .map_err(|_| Error::INVALID_PARAMETER)?;
+        let reference = reference.as_ref().map(|reference| create_unicode_string(reference.as_slice()));
+
+        let reference_ptr = reference.as_ref()
+            .map(|reference| reference as *const UNICODE_STRING)
+            .unwrap_or(core::ptr::null());
+
+        let mut link: UNICODE_STRING = core::mem::zeroed();
+
+        IoRegisterDeviceInterface(
+            physical_device,
+            class_guid.as_raw(),
+            reference_ptr as _,
+            &mut link,
+        ).into_result()?;
+
+        // Copy the symbolic link name, such that the buffer allocated by the I/O manager can be
+        // freed right away.
+        let buffer = core::slice::from_raw_parts(link.Buffer, (link.Length / 2) as usize);
+        let name = U16CString::from_vec_truncate(buffer.to_vec());
+
+        let result = IoSetDeviceInterfaceState(&mut link, true as _).into_result();
+
+        RtlFreeUnicodeString(&mut link);
+
+        result?;
+
+        Ok(Self {
+            name,
+        })
+    }
+
+    /// Returns the symbolic link name of the device interface, which can be passed to `CreateFile`
+    /// in user mode.
+    pub fn symbolic_link_name(&self) -> String {
+        self.name.to_string_lossy()
+    }
+}
+
+impl Drop for DeviceInterface {
+    fn drop(&mut self) {
+        let mut name_ptr = create_unicode_string(self.name.as_slice());
+
+        unsafe {
+            IoSetDeviceInterfaceState(&mut name_ptr, false as _);
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! This module provides Deferred Procedure Calls (DPCs), which run a closure at `DISPATCH_LEVEL`
+//! shortly after they have been queued, e.g. to finish work that was started by an interrupt or a
+//! timer.
+
+use alloc::boxed::Box;
+use core::cell::UnsafeCell;
+use crate::affinity::get_processor_number;
+use crate::error::{Error, IntoResult};
+use crate::irql::{current_irql, PASSIVE_LEVEL};
+use windows_kernel_sys::base::{KDPC, PKDPC, PVOID};
+use windows_kernel_sys::base::_KDPC_IMPORTANCE as KDPC_IMPORTANCE;
+use windows_kernel_sys::ntoskrnl::{
+    KeFlushQueuedDpcs, KeInitializeDpc, KeInitializeThreadedDpc, KeInsertQueueDpc,
+    KeIsExecutingDpc, KeRemoveQueueDpc, KeSetImportanceDpc, KeSetTargetProcessorDpcEx,
+};
+
+/// The importance of a DPC, which determines where the DPC is queued and how soon the DPC queue
+/// is drained.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DpcImportance {
+    /// The DPC is queued at the end of the DPC queue, and the queue is not necessarily drained
+    /// right away.
+    Low,
+    /// The DPC is queued at the end of the DPC queue. This is the default.
+    Medium,
+    /// The DPC is queued at the end of the DPC queue, and the queue is drained right away even if
+    /// the DPC targets another processor.
+    MediumHigh,
+    /// The DPC is queued at the start of the DPC queue.
+    High,
+}
+
+impl DpcImportance {
+    fn as_raw(self) -> KDPC_IMPORTANCE::Type {
+        match self {
+            DpcImportance::Low => KDPC_IMPORTANCE::LowImportance,
+            DpcImportance::Medium => KDPC_IMPORTANCE::MediumImportance,
+            DpcImportance::MediumHigh => KDPC_IMPORTANCE::MediumHighImportance,
+            DpcImportance::High => KDPC_IMPORTANCE::HighImportance,
+        }
+    }
+}
+
+struct DpcInner {
+    raw: UnsafeCell<KDPC>,
+    f: Box<dyn Fn() + Send + Sync>,
+}
+
+/// This is the deferred routine used by [`Dpc`] to run the closure.
+unsafe extern "C" fn dpc_routine(
+    _dpc: PKDPC,
+    context: PVOID,
+    _argument1: PVOID,
+    _argument2: PVOID,
+) {
+    let inner = &*(context as *const DpcInner);
+
+    (inner.f)();
+}
+
+/// A Deferred Procedure Call (DPC) that runs a closure at `DISPATCH_LEVEL` after it has been
+/// queued through [`queue`]. A threaded DPC, as created by [`new_threaded`], runs at
+/// `PASSIVE_LEVEL` instead, unless threaded DPCs have been disabled on the system.
+///
+/// The closure must not block or access paged memory. As the DPC can be queued again while the
+/// closure is running, the closure may run on multiple processors at the same time.
+///
+/// Cancelling or dropping the DPC waits for the closure to finish through `KeFlushQueuedDpcs`.
+/// This is a hard requirement: it bugchecks at `DISPATCH_LEVEL` and deadlocks when called from
+/// any DPC, including the closure of the DPC itself. Hence [`cancel`] and dropping the DPC panic
+/// unless they are called at `PASSIVE_LEVEL` outside of a DPC, also in release builds.
+///
+/// [`cancel`]: Dpc::cancel
+///
+/// [`queue`]: Dpc::queue
+/// [`new_threaded`]: Dpc::new_threaded
+pub struct Dpc {
+    // The `KDPC` is linked into the DPC queue of a processor while it is queued, hence it must not
+    // move.
+    inner: Box<DpcInner>,
+}
+
+unsafe impl Send for Dpc {}
+unsafe impl Sync for Dpc {}
+
+impl Dpc {
+    fn from_closure<F>(f: F, threaded: bool) -> Self
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        let inner = Box::new(DpcInner {
+            raw: UnsafeCell::new(unsafe { core::mem::zeroed() }),
+            f: Box::new(f),
+        });
+
+        let context = &*inner as *const DpcInner as PVOID;
+
+        unsafe {
+            match threaded {
+                true => KeInitializeThreadedDpc(inner.raw.get(), Some(dpc_routine), context),
+                _ => KeInitializeDpc(inner.raw.get(), Some(dpc_routine), context),
+            }
+        };
+
+        Self {
+            inner,
+        }
+    }
+
+    /// Creates a DPC that runs the closure at `DISPATCH_LEVEL`.
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        Self::from_closure(f, false)
+    }
+
+    /// Creates a threaded DPC that runs the closure on a real-time priority thread at
+    /// `PASSIVE_LEVEL`, or at `DISPATCH_LEVEL` if threaded DPCs have been disabled on the system.
+    /// Hence the closure must meet the requirements of both.
+    pub fn new_threaded<F>(f: F) -> Self
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        Self::from_closure(f, true)
+    }
+
+    pub fn as_ptr(&self) -> PKDPC {
+        self.inner.raw.get()
+    }
+
+    /// Makes the DPC run on the CPU with the given logical CPU number, rather than on the CPU that
+    /// queues the DPC.
+    pub fn set_target_processor(&mut self, cpu_num: u32) -> Result<(), Error> {
+        let mut processor_num = get_processor_number(cpu_num)?;
+
+        unsafe {
+            KeSetTargetProcessorDpcEx(
+                self.inner.raw.get(),
+                &mut processor_num,
+            )
+        }.into_result()
+    }
+
+    /// Sets the importance of the DPC.
+    pub fn set_importance(&mut self, importance: DpcImportance) {
+        unsafe {
+            KeSetImportanceDpc(
+                self.inner.raw.get(),
+                importance.as_raw(),
+            )
+        };
+    }
+
+    /// Queues the DPC. Returns `false` if the DPC was already queued, in which case the closure
+    /// only runs once. Can be called at any IRQL.
+    pub fn queue(&self) -> bool {
+        unsafe {
+            KeInsertQueueDpc(
+                self.inner.raw.get(),
+                core::ptr::null_mut(),
+                core::ptr::null_mut(),
+            ) != 0
+        }
+    }
+
+    /// Removes the DPC from the DPC queue, and waits for the closure to finish on any processor
+    /// it is running on. Returns `true` if the DPC was queued.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called at an IRQL other than `PASSIVE_LEVEL` or from a DPC, such as the closure
+    /// of a threaded DPC, as waiting for the closure would bugcheck or deadlock.
+    pub fn cancel(&self) -> bool {
+        assert!(
+            current_irql() == PASSIVE_LEVEL && unsafe { KeIsExecutingDpc() } == 0,
+            "a DPC can only be cancelled or dropped at PASSIVE_LEVEL outside of a DPC",
+        );
+
+        let removed = unsafe {
+            KeRemoveQueueDpc(
+                self.inner.raw.get(),
+            )
+        } != 0;
+
+        unsafe {
+            KeFlushQueuedDpcs()
+        };
+
+        removed
+    }
+}
+
+impl Drop for Dpc {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
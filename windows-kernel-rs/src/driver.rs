@@ -1,10 +1,34 @@
-use alloc::boxed::Box;
-use crate::device::{Access, Device, DeviceExtension, DeviceDoFlags, DeviceFlags, DeviceOperations, DeviceOperationsVtable, DeviceType};
+use core::sync::atomic::{AtomicPtr, Ordering};
+use crate::device::{Access, Device, DeviceBuilder, DeviceDoFlags, DeviceFlags, DeviceOperations, DeviceType};
 use crate::error::{Error, IntoResult};
 use crate::string::create_unicode_string;
 use widestring::U16CString;
-use windows_kernel_sys::base::DRIVER_OBJECT;
-use windows_kernel_sys::ntoskrnl::{IoCreateDevice};
+use windows_kernel_sys::base::{DEVICE_OBJECT, DRIVER_OBJECT, FILE_OBJECT, FILE_READ_ATTRIBUTES};
+use windows_kernel_sys::base::{DO_BUFFERED_IO, DO_DEVICE_INITIALIZING, DO_DIRECT_IO, DO_POWER_PAGABLE};
+use windows_kernel_sys::ntoskrnl::{IoAttachDeviceToDeviceStackSafe, IoGetDeviceObjectPointer};
+use windows_kernel_sys::ntoskrnl::ObDereferenceObject;
+
+/// The driver object of this driver, see [`init`].
+static DRIVER_OBJECT: AtomicPtr<DRIVER_OBJECT> = AtomicPtr::new(core::ptr::null_mut());
+
+/// Records the driver object of this driver, such that system threads can keep the driver loaded
+/// for as long as they run. The [`kernel_module!`] macro calls this before any code of the
+/// driver runs. Drivers that define their own `DriverEntry` should call this first, otherwise
+/// spawning threads fails.
+///
+/// # Safety
+///
+/// The driver object must be the one that was passed to `DriverEntry`.
+///
+/// [`kernel_module!`]: crate::kernel_module
+pub unsafe fn init(driver: *mut DRIVER_OBJECT) {
+    DRIVER_OBJECT.store(driver, Ordering::Release);
+}
+
+/// Returns the driver object recorded by [`init`], or a null pointer if it has not been recorded.
+pub(crate) fn driver_object() -> *mut DRIVER_OBJECT {
+    DRIVER_OBJECT.load(Ordering::Acquire)
+}
 
 pub struct Driver {
     pub(crate) raw: *mut DRIVER_OBJECT,
@@ -37,39 +61,92 @@ impl Driver {
     where
         T: DeviceOperations
     {
-        // Box the data.
-        let data = Box::new(data);
+        DeviceBuilder::new(device_type)
+            .name(name)
+            .characteristics(device_flags)
+            .do_flags(device_do_flags)
+            .access(access)
+            .build(self, data)
+    }
 
+    /// Creates an unnamed device and uses [`IoAttachDeviceToDeviceStackSafe`] to attach it to the
+    /// device stack of the device with the given name, e.g. to implement a filter driver. The
+    /// device type, characteristics and I/O flags are copied from the device that the new device
+    /// is attached to.
+    ///
+    /// Any requests that are not handled by the [`DeviceOperations`] of the new device are passed
+    /// on to the lower device. The device is detached from the device stack when it is dropped.
+    ///
+    /// The `FsContext` of file objects belongs to the lower device, hence the device cannot have
+    /// a [`DeviceOperations::FileContext`].
+    pub fn attach_device<T>(
+        &mut self,
+        target: &str,
+        data: T,
+    ) -> Result<Device, Error>
+    where
+        T: DeviceOperations<FileContext = ()>
+    {
         // Convert the name to UTF-16 and then create a UNICODE_STRING.
-        let name = U16CString::from_str(name).unwrap();
-        let mut name = create_unicode_string(name.as_slice());
+        let target = U16CString::from_str(target).unwrap();
+        let mut target = create_unicode_string(target.as_slice());
 
-        // Create the device.
-        let mut device = core::ptr::null_mut();
+        // Look up the device object by its name.
+        let mut file_object: *mut FILE_OBJECT = core::ptr::null_mut();
+        let mut target_device: *mut DEVICE_OBJECT = core::ptr::null_mut();
 
         unsafe {
-            IoCreateDevice(
-                self.raw,
-                core::mem::size_of::<DeviceExtension>() as u32,
-                &mut name,
-                device_type.into(),
-                device_flags.bits(),
-                access.is_exclusive() as _,
-                &mut device,
+            IoGetDeviceObjectPointer(
+                &mut target,
+                FILE_READ_ATTRIBUTES,
+                &mut file_object,
+                &mut target_device,
             )
         }.into_result()?;
 
+        let result = self.attach_to_device(target_device, data);
+
+        // The device object remains referenced through the device stack that we attached to.
         unsafe {
-            (*device).Flags |= device_do_flags.bits();
+            ObDereferenceObject(file_object as _);
         }
 
-        let device = unsafe { Device::from_raw(device) };
+        result
+    }
 
-        // Store the boxed data and vtable.
-        let extension = device.extension_mut();
-        extension.device_type = device_type;
-        extension.vtable = &DeviceOperationsVtable::<T>::VTABLE;
-        extension.data = Box::into_raw(data) as *mut cty::c_void;
+    fn attach_to_device<T>(
+        &mut self,
+        target_device: *mut DEVICE_OBJECT,
+        data: T,
+    ) -> Result<Device, Error>
+    where
+        T: DeviceOperations<FileContext = ()>
+    {
+        let (device_type, characteristics) = unsafe {
+            ((*target_device).DeviceType, (*target_device).Characteristics)
+        };
+
+        let device = DeviceBuilder::from_raw_parts(device_type, characteristics)
+            .build(self, data)?;
+
+        let mut lower_device = core::ptr::null_mut();
+
+        unsafe {
+            IoAttachDeviceToDeviceStackSafe(
+                device.as_raw_mut(),
+                target_device,
+                &mut lower_device,
+            )
+        }.into_result()?;
+
+        device.extension_mut().lower_device = lower_device;
+
+        unsafe {
+            let raw = device.as_raw_mut();
+
+            (*raw).Flags |= (*lower_device).Flags & (DO_BUFFERED_IO | DO_DIRECT_IO | DO_POWER_PAGABLE);
+            (*raw).Flags &= !DO_DEVICE_INITIALIZING;
+        }
 
         Ok(device)
     }
@@ -1,3 +1,4 @@
+use windows_kernel_common::pod::PodError;
 use windows_kernel_sys::base::NTSTATUS;
 use windows_kernel_sys::base::{
     STATUS_SUCCESS,
@@ -12,11 +13,15 @@ use windows_kernel_sys::base::{
     STATUS_IN_PAGE_ERROR,
     STATUS_INVALID_HANDLE,
     STATUS_INVALID_PARAMETER,
+    STATUS_INVALID_DEVICE_REQUEST,
     STATUS_END_OF_FILE,
     STATUS_NO_MEMORY,
     STATUS_ILLEGAL_INSTRUCTION,
+    STATUS_ACCESS_DENIED,
     STATUS_NONCONTINUABLE_EXCEPTION,
     STATUS_INVALID_DISPOSITION,
+    STATUS_SEMAPHORE_LIMIT_EXCEEDED,
+    STATUS_DELETE_PENDING,
     STATUS_ARRAY_BOUNDS_EXCEEDED,
     STATUS_FLOAT_DENORMAL_OPERAND,
     STATUS_FLOAT_DIVIDE_BY_ZERO,
@@ -29,8 +34,11 @@ use windows_kernel_sys::base::{
     STATUS_INTEGER_OVERFLOW,
     STATUS_PRIVILEGED_INSTRUCTION,
     STATUS_INSUFFICIENT_RESOURCES,
+    STATUS_IO_TIMEOUT,
+    STATUS_NOT_SUPPORTED,
     STATUS_INVALID_USER_BUFFER,
     STATUS_STACK_OVERFLOW,
+    STATUS_CANCELLED,
 };
 
 #[derive(Clone, Copy, Debug)]
@@ -48,11 +56,15 @@ impl Error {
     pub const IN_PAGE_ERROR:            Error = Error(STATUS_IN_PAGE_ERROR);
     pub const INVALID_HANDLE:           Error = Error(STATUS_INVALID_HANDLE);
     pub const INVALID_PARAMETER:        Error = Error(STATUS_INVALID_PARAMETER);
+    pub const INVALID_DEVICE_REQUEST:   Error = Error(STATUS_INVALID_DEVICE_REQUEST);
     pub const END_OF_FILE:              Error = Error(STATUS_END_OF_FILE);
     pub const NO_MEMORY:                Error = Error(STATUS_NO_MEMORY);
     pub const ILLEGAL_INSTRUCTION:      Error = Error(STATUS_ILLEGAL_INSTRUCTION);
+    pub const ACCESS_DENIED:            Error = Error(STATUS_ACCESS_DENIED);
     pub const NONCONTINUABLE_EXCEPTION: Error = Error(STATUS_NONCONTINUABLE_EXCEPTION);
     pub const INVALID_DISPOSITION:      Error = Error(STATUS_INVALID_DISPOSITION);
+    pub const SEMAPHORE_LIMIT_EXCEEDED: Error = Error(STATUS_SEMAPHORE_LIMIT_EXCEEDED);
+    pub const DELETE_PENDING:           Error = Error(STATUS_DELETE_PENDING);
     pub const ARRAY_BOUNDS_EXCEEDED:    Error = Error(STATUS_ARRAY_BOUNDS_EXCEEDED);
     pub const FLOAT_DENORMAL_OPERAND:   Error = Error(STATUS_FLOAT_DENORMAL_OPERAND);
     pub const FLOAT_DIVIDE_BY_ZERO:     Error = Error(STATUS_FLOAT_DIVIDE_BY_ZERO);
@@ -65,8 +77,11 @@ impl Error {
     pub const INTEGER_OVERFLOW:         Error = Error(STATUS_INTEGER_OVERFLOW);
     pub const PRIVILEGED_INSTRUCTION:   Error = Error(STATUS_PRIVILEGED_INSTRUCTION);
     pub const INSUFFICIENT_RESOURCES:   Error = Error(STATUS_INSUFFICIENT_RESOURCES);
+    pub const IO_TIMEOUT:               Error = Error(STATUS_IO_TIMEOUT);
+    pub const NOT_SUPPORTED:            Error = Error(STATUS_NOT_SUPPORTED);
     pub const INVALID_USER_BUFFER:      Error = Error(STATUS_INVALID_USER_BUFFER);
     pub const STACK_OVERFLOW:           Error = Error(STATUS_STACK_OVERFLOW);
+    pub const CANCELLED:                Error = Error(STATUS_CANCELLED);
 
     pub fn from_ntstatus(status: NTSTATUS) -> Error {
         Error(status)
@@ -77,6 +92,16 @@ impl Error {
     }
 }
 
+impl From<PodError> for Error {
+    fn from(error: PodError) -> Self {
+        match error {
+            PodError::InvalidSize => Error::INVALID_USER_BUFFER,
+            PodError::Misaligned => Error::DATATYPE_MISALIGNMENT,
+            PodError::OutOfMemory => Error::INSUFFICIENT_RESOURCES,
+        }
+    }
+}
+
 pub trait IntoResult {
     fn into_result(self) -> Result<(), Error>;
 }
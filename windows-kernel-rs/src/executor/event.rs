@@ -0,0 +1,96 @@
+use alloc::vec::Vec;
+use core::future::Future;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::{Context, Poll, Waker};
+use crate::sync::spin_lock::SpinLock;
+
+/// An event that futures can wait for, e.g. to tell the futures spawned on an executor to stop.
+/// Like a notification [`Event`], the event stays set until it gets reset, and setting it wakes up
+/// all of the futures waiting for it.
+///
+/// The event can be set and reset at `IRQL <= DISPATCH_LEVEL`.
+///
+/// [`Event`]: crate::sync::event::Event
+pub struct AsyncEvent {
+    set: AtomicBool,
+    waiters: SpinLock<Vec<Waker>>,
+}
+
+impl AsyncEvent {
+    /// Creates an event that is not set.
+    pub const fn new() -> Self {
+        Self {
+            set: AtomicBool::new(false),
+            waiters: SpinLock::new(Vec::new()),
+        }
+    }
+
+    /// Sets the event, and wakes up all of the futures waiting for it.
+    pub fn set(&self) {
+        self.set.store(true, Ordering::Release);
+
+        // Wake up the futures after releasing the lock, as waking them up may schedule them.
+        let waiters = core::mem::take(&mut *self.waiters.lock());
+
+        for waker in waiters {
+            waker.wake();
+        }
+    }
+
+    /// Resets the event.
+    pub fn reset(&self) {
+        self.set.store(false, Ordering::Release);
+    }
+
+    /// Returns whether the event is set.
+    pub fn is_set(&self) -> bool {
+        self.set.load(Ordering::Acquire)
+    }
+
+    /// Returns a future that completes once the event is set.
+    pub fn wait(&self) -> EventWait<'_> {
+        EventWait {
+            event: self,
+        }
+    }
+}
+
+impl Default for AsyncEvent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A future that completes once the event is set.
+///
+/// This structure is created by the [`wait`] method on [`AsyncEvent`].
+///
+/// [`wait`]: AsyncEvent::wait
+pub struct EventWait<'a> {
+    event: &'a AsyncEvent,
+}
+
+impl<'a> Future for EventWait<'a> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.event.is_set() {
+            return Poll::Ready(());
+        }
+
+        {
+            let mut waiters = self.event.waiters.lock();
+
+            if !waiters.iter().any(|waker| waker.will_wake(cx.waker())) {
+                waiters.push(cx.waker().clone());
+            }
+        }
+
+        // The event may have been set before the waker got registered.
+        match self.event.is_set() {
+            true => Poll::Ready(()),
+            _ => Poll::Pending,
+        }
+    }
+}
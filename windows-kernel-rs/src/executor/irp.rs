@@ -0,0 +1,130 @@
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use crate::device::Device;
+use crate::error::{Error, IntoResult};
+use crate::executor::signal::Signal;
+use windows_kernel_sys::base::{DEVICE_OBJECT, IRP, NTSTATUS, PVOID};
+use windows_kernel_sys::base::STATUS_MORE_PROCESSING_REQUIRED;
+use windows_kernel_sys::ntoskrnl::{IoCallDriver, IoCancelIrp, IoSetCompletionRoutineEx};
+
+/// This is the completion routine used by [`send_irp`] to wake up the future.
+unsafe extern "C" fn completion_routine(
+    _device: *mut DEVICE_OBJECT,
+    _irp: *mut IRP,
+    context: PVOID,
+) -> NTSTATUS {
+    let signal = Box::from_raw(context as *mut Arc<Signal>);
+
+    signal.notify();
+
+    // The IRP belongs to the caller of `send_irp`, hence stop the I/O manager from completing it
+    // any further.
+    STATUS_MORE_PROCESSING_REQUIRED
+}
+
+/// Sends the IRP to the target device, and returns a future that completes with the result of
+/// the IRP once the lower driver has completed it. Can be called at `IRQL <= DISPATCH_LEVEL`.
+///
+/// The completion routine is set through `IoSetCompletionRoutineEx`, which keeps the given device
+/// of this driver referenced until the completion routine has returned, such that the driver
+/// cannot be unloaded in the meantime. If that fails, the IRP is not sent and the future
+/// completes with the error.
+///
+/// ```ignore
+/// let irp = IoAllocateIrp(target.StackSize, false as _);
+/// // Set up the next stack location of the IRP.
+///
+/// let result = unsafe { executor::send_irp(&device, target, irp) }.await;
+/// IoFreeIrp(irp);
+/// ```
+///
+/// # Safety
+///
+/// The IRP must have been allocated by the caller, e.g. through `IoAllocateIrp`, with its next
+/// stack location set up for the target device. The caller owns the IRP again once the future
+/// completes, and must not free or reuse the IRP before, even if the future gets dropped.
+pub unsafe fn send_irp(
+    device: &Device,
+    target: *mut DEVICE_OBJECT,
+    irp: *mut IRP,
+) -> IrpCompletion {
+    let signal = Arc::new(Signal::new());
+    let context = Box::into_raw(Box::new(signal.clone()));
+
+    let result = IoSetCompletionRoutineEx(
+        device.as_raw_mut(),
+        irp,
+        Some(completion_routine),
+        context as _,
+        true as _,
+        true as _,
+        true as _,
+    ).into_result();
+
+    if let Err(e) = result {
+        drop(Box::from_raw(context));
+
+        (*irp).IoStatus.__bindgen_anon_1.Status = e.to_ntstatus();
+        (*irp).IoStatus.Information = 0;
+        signal.notify();
+
+        return IrpCompletion {
+            irp,
+            signal,
+        };
+    }
+
+    // The status is also returned through the IRP, once it has been completed.
+    let _ = IoCallDriver(target, irp);
+
+    IrpCompletion {
+        irp,
+        signal,
+    }
+}
+
+/// A future that completes once the lower driver has completed the IRP.
+///
+/// This structure is created by the [`send_irp`] function.
+pub struct IrpCompletion {
+    irp: *mut IRP,
+    signal: Arc<Signal>,
+}
+
+unsafe impl Send for IrpCompletion {}
+
+impl IrpCompletion {
+    /// Asks the lower driver to cancel the IRP. The future still completes once the lower driver
+    /// has completed the IRP, typically with [`Error::CANCELLED`]. Returns `false` if the IRP
+    /// cannot be cancelled.
+    pub fn cancel(&self) -> bool {
+        if self.signal.is_notified() {
+            return false;
+        }
+
+        unsafe {
+            IoCancelIrp(self.irp)
+        } != 0
+    }
+}
+
+impl Future for IrpCompletion {
+    type Output = Result<u32, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.signal.poll(cx).is_pending() {
+            return Poll::Pending;
+        }
+
+        let irp = unsafe { &*self.irp };
+        let status = unsafe { irp.IoStatus.__bindgen_anon_1.Status };
+
+        Poll::Ready(match status {
+            status if status >= 0 => Ok(irp.IoStatus.Information as _),
+            status => Err(Error::from_ntstatus(status)),
+        })
+    }
+}
@@ -0,0 +1,70 @@
+//! This module provides a small executor for futures, such that multi-step protocols like sending
+//! a request to a lower driver, waiting for it to complete, and retrying it after a delay, can be
+//! written with `async` and `await` rather than as state machines. This module requires the
+//! `async` feature.
+//!
+//! Futures are spawned on either a [`ThreadExecutor`], which polls them on a dedicated system
+//! thread, or a [`WorkItemExecutor`], which polls them on the system worker threads. Both poll the
+//! futures at `PASSIVE_LEVEL`, while the futures can be woken up at `IRQL <= DISPATCH_LEVEL`, e.g.
+//! from DPCs and completion routines. [`block_on`] polls a future on the current thread instead.
+//!
+//! The scheduling logic in the [`task`] module lives in `windows-kernel-common`, as it does not
+//! depend on the kernel and can be tested on the host.
+
+pub mod event;
+pub mod irp;
+pub(crate) mod signal;
+pub mod sleep;
+pub use windows_kernel_common::task;
+pub mod thread_executor;
+pub mod work_item_executor;
+
+pub use self::event::AsyncEvent;
+pub use self::irp::{send_irp, IrpCompletion};
+pub use self::sleep::{sleep, Sleep};
+pub use self::thread_executor::ThreadExecutor;
+pub use self::work_item_executor::WorkItemExecutor;
+
+use alloc::sync::Arc;
+use alloc::task::Wake;
+use core::future::Future;
+use core::task::{Context, Poll, Waker};
+use crate::sync::event::Event;
+use crate::sync::wait::Waitable;
+
+/// A waker backed by a synchronization `KEVENT`, which the thread in [`block_on`] waits for.
+struct EventWaker {
+    event: Event,
+}
+
+impl Wake for EventWaker {
+    fn wake(self: Arc<Self>) {
+        self.event.set();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.event.set();
+    }
+}
+
+/// Polls the future on the current thread until it completes, blocking the thread while the
+/// future cannot make progress. Can only be called at `IRQL <= APC_LEVEL`.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    crate::debug_assert_irql!(<= APC_LEVEL);
+
+    let event_waker = Arc::new(EventWaker {
+        event: Event::synchronization(),
+    });
+
+    let waker = Waker::from(event_waker.clone());
+    let mut cx = Context::from_waker(&waker);
+    let mut future = core::pin::pin!(future);
+
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+
+        event_waker.event.wait();
+    }
+}
@@ -0,0 +1,58 @@
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::{Context, Poll, Waker};
+use crate::sync::spin_lock::SpinLock;
+
+/// A one-shot notification that wakes up the task waiting for it, e.g. from a DPC or a completion
+/// routine. Can be used at `IRQL <= DISPATCH_LEVEL`.
+pub(crate) struct Signal {
+    notified: AtomicBool,
+    waker: SpinLock<Option<Waker>>,
+}
+
+impl Signal {
+    pub(crate) const fn new() -> Self {
+        Self {
+            notified: AtomicBool::new(false),
+            waker: SpinLock::new(None),
+        }
+    }
+
+    /// Returns whether the signal has been notified.
+    pub(crate) fn is_notified(&self) -> bool {
+        self.notified.load(Ordering::Acquire)
+    }
+
+    /// Marks the signal as notified, and wakes up the task that is waiting for it, if any.
+    pub(crate) fn notify(&self) {
+        self.notified.store(true, Ordering::Release);
+
+        // Wake up the task after releasing the lock, as waking it up may schedule it.
+        let waker = self.waker.lock().take();
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    /// Returns whether the signal has been notified. Otherwise, registers the waker of the task
+    /// to be woken up once the signal gets notified.
+    pub(crate) fn poll(&self, cx: &mut Context<'_>) -> Poll<()> {
+        if self.is_notified() {
+            return Poll::Ready(());
+        }
+
+        {
+            let mut waker = self.waker.lock();
+
+            if !waker.as_ref().is_some_and(|waker| waker.will_wake(cx.waker())) {
+                *waker = Some(cx.waker().clone());
+            }
+        }
+
+        // The signal may have been notified before the waker got registered.
+        match self.is_notified() {
+            true => Poll::Ready(()),
+            _ => Poll::Pending,
+        }
+    }
+}
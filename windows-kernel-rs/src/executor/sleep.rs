@@ -0,0 +1,42 @@
+use alloc::sync::Arc;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use crate::executor::signal::Signal;
+use crate::timer::Timer;
+
+/// A future that completes once the given duration has passed.
+///
+/// This structure is created by the [`sleep`] function.
+pub struct Sleep {
+    signal: Arc<Signal>,
+    _timer: Timer,
+}
+
+/// Returns a future that completes once the given duration has passed, which is backed by a
+/// kernel [`Timer`]. The future has to be dropped at `PASSIVE_LEVEL`, which is the case for the
+/// executors in this module.
+pub fn sleep(duration: Duration) -> Sleep {
+    let signal = Arc::new(Signal::new());
+
+    let timer = {
+        let signal = signal.clone();
+        Timer::new(move || signal.notify())
+    };
+
+    timer.schedule(duration);
+
+    Sleep {
+        signal,
+        _timer: timer,
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        self.signal.poll(cx)
+    }
+}
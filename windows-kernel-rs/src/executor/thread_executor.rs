@@ -0,0 +1,138 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::future::Future;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use crate::error::Error;
+use crate::executor::task::{Schedule, Task};
+use crate::sync::event::Event;
+use crate::sync::slist::SList;
+use crate::sync::wait::Waitable;
+use crate::thread::{self, JoinHandle};
+
+struct Shared {
+    queue: SList<Arc<Task<ThreadScheduler>>>,
+    event: Event,
+    tasks: AtomicUsize,
+    shutdown: AtomicBool,
+}
+
+/// The scheduler of the tasks spawned on a [`ThreadExecutor`], which queues the tasks for the
+/// thread of the executor. Tasks can be scheduled at `IRQL <= DISPATCH_LEVEL`.
+pub struct ThreadScheduler {
+    shared: Arc<Shared>,
+}
+
+impl Schedule for ThreadScheduler {
+    fn schedule(&self, task: Arc<Task<Self>>) {
+        // Only wake up the thread for the first task, the thread takes all of the tasks at once.
+        if self.shared.queue.push(task) {
+            self.shared.event.set();
+        }
+    }
+}
+
+// Every task owns its scheduler, hence the scheduler is dropped along with the task, whether its
+// future has completed or the task was dropped before, e.g. because its waker was dropped.
+impl Drop for ThreadScheduler {
+    fn drop(&mut self) {
+        let tasks = self.shared.tasks.fetch_sub(1, Ordering::AcqRel) - 1;
+
+        if tasks == 0 && self.shared.shutdown.load(Ordering::Acquire) {
+            self.shared.event.set();
+        }
+    }
+}
+
+fn run(shared: Arc<Shared>) {
+    loop {
+        // The queue returns the tasks from the most recently scheduled one to the least recently
+        // scheduled one, hence run them in reverse.
+        let tasks: Vec<_> = shared.queue.pop_all().collect();
+
+        if tasks.is_empty() {
+            if shared.shutdown.load(Ordering::Acquire) && shared.tasks.load(Ordering::Acquire) == 0 {
+                break;
+            }
+
+            shared.event.wait();
+            continue;
+        }
+
+        for task in tasks.into_iter().rev() {
+            task.run();
+        }
+    }
+}
+
+/// An executor that runs futures on a dedicated system thread, e.g. for long-running protocols
+/// that should not occupy the system worker threads.
+///
+/// ```ignore
+/// let executor = ThreadExecutor::new()?;
+///
+/// executor.spawn(async move {
+///     loop {
+///         executor::sleep(Duration::from_secs(1)).await;
+///         sample();
+///     }
+/// });
+/// ```
+///
+/// Dropping the executor waits for all of the spawned tasks to be dropped, i.e. for their futures
+/// to complete or for nothing to be able to wake them up anymore, hence the futures should be
+/// told to stop first, e.g. through an [`AsyncEvent`].
+///
+/// [`AsyncEvent`]: crate::executor::event::AsyncEvent
+pub struct ThreadExecutor {
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ThreadExecutor {
+    /// Creates an executor and spawns its thread. This function can only be called at
+    /// `PASSIVE_LEVEL`.
+    pub fn new() -> Result<Self, Error> {
+        let shared = Arc::new(Shared {
+            queue: SList::new(),
+            event: Event::synchronization(),
+            tasks: AtomicUsize::new(0),
+            shutdown: AtomicBool::new(false),
+        });
+
+        let thread = {
+            let shared = shared.clone();
+            thread::spawn(move || run(shared))?
+        };
+
+        Ok(Self {
+            shared,
+            thread: Some(thread),
+        })
+    }
+
+    /// Spawns the future on the thread of the executor. Can be called at
+    /// `IRQL <= DISPATCH_LEVEL`.
+    pub fn spawn<F>(&self, future: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.shared.tasks.fetch_add(1, Ordering::AcqRel);
+
+        let scheduler = ThreadScheduler {
+            shared: self.shared.clone(),
+        };
+
+        Task::spawn(scheduler, future);
+    }
+}
+
+impl Drop for ThreadExecutor {
+    fn drop(&mut self) {
+        self.shared.shutdown.store(true, Ordering::Release);
+        self.shared.event.set();
+
+        if let Some(thread) = self.thread.take() {
+            thread.join();
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use alloc::sync::Arc;
+use core::future::Future;
+use crate::device::Device;
+use crate::error::Error;
+use crate::executor::task::{Schedule, Task};
+use crate::sync::rundown::DRIVER_RUNDOWN;
+use crate::workitem::WorkQueue;
+use windows_kernel_sys::base::{DEVICE_OBJECT, PIO_WORKITEM, PVOID};
+use windows_kernel_sys::ntoskrnl::{IoAllocateWorkItem, IoFreeWorkItem, IoQueueWorkItemEx};
+use windows_kernel_sys::ntoskrnl::{ObDereferenceObject, ObReferenceObject};
+
+/// The scheduler of a task spawned through a [`WorkItemExecutor`], which queues a work item that
+/// runs the task. Every task has its own work item, as a task is only scheduled once at a time,
+/// hence scheduling the task never fails. Tasks can be scheduled at `IRQL <= DISPATCH_LEVEL`.
+pub struct WorkItemScheduler {
+    device: *mut DEVICE_OBJECT,
+    work_item: PIO_WORKITEM,
+    queue: WorkQueue,
+}
+
+unsafe impl Send for WorkItemScheduler {}
+unsafe impl Sync for WorkItemScheduler {}
+
+/// This is the worker routine used by [`WorkItemScheduler`] to run the task.
+unsafe extern "C" fn work_item_routine(
+    _io_object: PVOID,
+    context: PVOID,
+    _work_item: PIO_WORKITEM,
+) {
+    let task = Arc::from_raw(context as *const Task<WorkItemScheduler>);
+
+    task.run();
+}
+
+impl Schedule for WorkItemScheduler {
+    fn schedule(&self, task: Arc<Task<Self>>) {
+        // The work item can be queued again as soon as the worker routine has started running.
+        unsafe {
+            IoQueueWorkItemEx(
+                self.work_item,
+                Some(work_item_routine),
+                self.queue.as_raw(),
+                Arc::into_raw(task) as _,
+            );
+        }
+    }
+}
+
+impl Drop for WorkItemScheduler {
+    fn drop(&mut self) {
+        unsafe {
+            IoFreeWorkItem(self.work_item);
+            ObDereferenceObject(self.device as _);
+        }
+    }
+}
+
+/// An executor that runs futures on the system worker threads, by queuing a work item whenever a
+/// future has to be polled.
+///
+/// ```ignore
+/// let executor = WorkItemExecutor::new(&device, WorkQueue::Delayed);
+///
+/// executor.spawn(async move {
+///     for _ in 0..3 {
+///         match send(&target).await {
+///             Ok(_) => break,
+///             _ => executor::sleep(Duration::from_millis(100)).await,
+///         }
+///     }
+/// })?;
+/// ```
+///
+/// The driver cannot be unloaded until all of the spawned futures have completed.
+pub struct WorkItemExecutor {
+    device: *mut DEVICE_OBJECT,
+    queue: WorkQueue,
+}
+
+unsafe impl Send for WorkItemExecutor {}
+unsafe impl Sync for WorkItemExecutor {}
+
+impl WorkItemExecutor {
+    /// Creates an executor that queues the work items for the device to the given queue.
+    pub fn new(device: &Device, queue: WorkQueue) -> Self {
+        let device = unsafe { device.as_raw_mut() };
+
+        unsafe {
+            ObReferenceObject(device as _);
+        }
+
+        Self {
+            device,
+            queue,
+        }
+    }
+
+    /// Spawns the future, which is first polled on a system worker thread. Returns
+    /// [`Error::DELETE_PENDING`] if the driver is being unloaded, and
+    /// [`Error::INSUFFICIENT_RESOURCES`] if the work item could not be allocated. Can be called at
+    /// `IRQL <= DISPATCH_LEVEL`.
+    pub fn spawn<F>(&self, future: F) -> Result<(), Error>
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let rundown = DRIVER_RUNDOWN.acquire()
+            .ok_or(Error::DELETE_PENDING)?;
+
+        let work_item = unsafe {
+            IoAllocateWorkItem(self.device)
+        };
+
+        if work_item.is_null() {
+            return Err(Error::INSUFFICIENT_RESOURCES);
+        }
+
+        unsafe {
+            ObReferenceObject(self.device as _);
+        }
+
+        let scheduler = WorkItemScheduler {
+            device: self.device,
+            work_item,
+            queue: self.queue,
+        };
+
+        // The future holds on to the run-time protection of the driver until it completes.
+        Task::spawn(scheduler, async move {
+            let _rundown = rundown;
+            future.await
+        });
+
+        Ok(())
+    }
+}
+
+impl Drop for WorkItemExecutor {
+    fn drop(&mut self) {
+        unsafe {
+            ObDereferenceObject(self.device as _);
+        }
+    }
+}
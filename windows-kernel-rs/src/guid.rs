@@ -0,0 +1,80 @@
+use core::fmt;
+use windows_kernel_common::guid;
+use windows_kernel_sys::base::GUID;
+
+/// A globally unique identifier, e.g. to identify a device class.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[repr(C)]
+pub struct Guid {
+    pub data1: u32,
+    pub data2: u16,
+    pub data3: u16,
+    pub data4: [u8; 8],
+}
+
+impl Guid {
+    pub const fn from_values(data1: u32, data2: u16, data3: u16, data4: [u8; 8]) -> Self {
+        Self {
+            data1,
+            data2,
+            data3,
+            data4,
+        }
+    }
+
+    /// Parses a GUID in the registry format, e.g. `{00000000-0000-0000-0000-000000000000}`. The
+    /// braces are optional. Returns `None` if the string is not a valid GUID.
+    ///
+    /// As this is a `const fn`, the [`guid!`] macro can use this to parse GUIDs at compile time.
+    pub const fn parse(s: &str) -> Option<Self> {
+        match guid::parse(s) {
+            Some((data1, data2, data3, data4)) => {
+                Some(Self::from_values(data1, data2, data3, data4))
+            }
+            None => None,
+        }
+    }
+
+    /// Returns a pointer to the GUID that can be passed to the kernel. The layout of [`Guid`] is
+    /// identical to the layout of [`GUID`].
+    pub fn as_raw(&self) -> *const GUID {
+        self as *const Guid as *const GUID
+    }
+}
+
+impl fmt::Display for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        guid::format(f, &(self.data1, self.data2, self.data3, self.data4))
+    }
+}
+
+/// Parses a GUID in the registry format at compile time, e.g.
+/// `guid!("{5f3d8a3e-2b1c-4a8e-9d4f-6c1b2a3d4e5f}")`. Invalid GUIDs result in a compilation error.
+#[macro_export]
+macro_rules! guid {
+    ($s:literal) => {{
+        const GUID: $crate::guid::Guid = match $crate::guid::Guid::parse($s) {
+            Some(guid) => guid,
+            None => panic!("invalid GUID"),
+        };
+
+        GUID
+    }};
+}
+
+impl From<GUID> for Guid {
+    fn from(guid: GUID) -> Self {
+        Self::from_values(guid.Data1, guid.Data2, guid.Data3, guid.Data4)
+    }
+}
+
+impl Into<GUID> for Guid {
+    fn into(self) -> GUID {
+        GUID {
+            Data1: self.data1,
+            Data2: self.data2,
+            Data3: self.data3,
+            Data4: self.data4,
+        }
+    }
+}
@@ -1,4 +1,7 @@
 use crate::device::DeviceType;
+use crate::error::Error;
+use crate::pod::Pod;
+use crate::user_ptr::UserPtr;
 use bitflags::bitflags;
 use windows_kernel_sys::base::{
     FILE_ANY_ACCESS, FILE_READ_DATA, FILE_WRITE_DATA, METHOD_BUFFERED, METHOD_IN_DIRECT,
@@ -114,3 +117,95 @@ impl Into<u32> for ControlCode {
         ty | access | num | method
     }
 }
+
+/// Reads the input of an I/O control code declared through [`ioctl_table!`].
+#[doc(hidden)]
+pub fn read_input<T: Pod>(user_ptr: &UserPtr) -> Result<T, Error> {
+    if core::mem::size_of::<T>() == 0 {
+        return Ok(unsafe { core::mem::zeroed() });
+    }
+
+    user_ptr.read()
+}
+
+/// Writes the output of an I/O control code declared through [`ioctl_table!`] and returns the
+/// number of bytes written.
+#[doc(hidden)]
+pub fn write_output<T: Pod>(user_ptr: &mut UserPtr, output: &T) -> Result<u32, Error> {
+    if core::mem::size_of::<T>() == 0 {
+        return Ok(0);
+    }
+
+    user_ptr.write(output)?;
+
+    Ok(core::mem::size_of::<T>() as u32)
+}
+
+/// Declares a set of I/O control codes along with their input and output types. The same
+/// declaration can be used with the `ioctl_table!` macro of the `winioctl` crate to generate the
+/// user mode client, e.g. by keeping the declaration in a separate file that is included by both
+/// the driver and the user mode application through [`include!`].
+///
+/// ```ignore
+/// ioctl_table! {
+///     pub ExampleIoctls: Unknown {
+///         print_value(0x800, ANY_ACCESS, Buffered): () => ();
+///         read_value(0x801, READ_DATA, Buffered): () => u32;
+///         write_value(0x802, WRITE_DATA, Buffered): u32 => ();
+///     }
+/// }
+/// ```
+///
+/// In the kernel this generates a trait with a handler for every I/O control code, that receives
+/// the input and returns the output. The provided `dispatch` method looks up the handler for the
+/// control code of an [`IoControlRequest`], reads the input from and writes the output to the
+/// [`UserPtr`] of the request, and returns the number of bytes written.
+///
+/// [`IoControlRequest`]: crate::request::IoControlRequest
+#[macro_export]
+macro_rules! ioctl_table {
+    (
+        $(#[$table_attr:meta])*
+        $vis:vis $table:ident: $dev_ty:ident {
+            $(
+                $(#[$attr:meta])*
+                $name:ident($nr:expr, $access:ident, $method:ident): $input:ty => $output:ty;
+            )*
+        }
+    ) => {
+        $(#[$table_attr])*
+        $vis trait $table {
+            $(
+                $(#[$attr])*
+                fn $name(
+                    &self,
+                    request: &$crate::IoControlRequest,
+                    input: $input,
+                ) -> Result<$output, $crate::Error>;
+            )*
+
+            /// Calls the handler for the I/O control code of the request. Returns the number of
+            /// bytes written to the output buffer.
+            fn dispatch(&self, request: &$crate::IoControlRequest) -> Result<u32, $crate::Error> {
+                let code = request.control_code();
+                let mut user_ptr = request.user_ptr();
+
+                $(
+                    if code == $crate::ControlCode(
+                        $crate::DeviceType::$dev_ty,
+                        $crate::RequiredAccess::$access,
+                        $nr,
+                        $crate::TransferMethod::$method,
+                    ) {
+                        let input: $input = $crate::ioctl::read_input(&user_ptr)?;
+                        let output: $output = self.$name(request, input)?;
+
+                        return $crate::ioctl::write_output(&mut user_ptr, &output);
+                    }
+                )*
+
+                Err($crate::Error::INVALID_DEVICE_REQUEST)
+            }
+        }
+    };
+}
@@ -0,0 +1,336 @@
+//! This module provides functions to query and change the Interrupt Request Level (IRQL) of the
+//! current processor, RAII guards for guarded and critical regions, and zero-sized tokens that
+//! document the IRQL that a function requires at compile time.
+//!
+//! Many functions of the kernel can only be called at certain IRQLs, e.g. blocking on a
+//! [`FastMutex`] requires `IRQL <= APC_LEVEL`. Violating these requirements results in a bug
+//! check, or worse, in a deadlock that only shows up under load. The [`debug_assert_irql!`] macro
+//! checks these requirements at runtime in debug builds.
+//!
+//! [`FastMutex`]: crate::sync::fast_mutex::FastMutex
+//! [`debug_assert_irql!`]: crate::debug_assert_irql
+
+use core::marker::PhantomData;
+use core::ops::Deref;
+use windows_kernel_sys::base::KIRQL;
+use windows_kernel_sys::ntoskrnl::{KeGetCurrentIrql, KeLowerIrql, KeRaiseIrql};
+use windows_kernel_sys::ntoskrnl::{KeEnterCriticalRegion, KeLeaveCriticalRegion};
+use windows_kernel_sys::ntoskrnl::{KeEnterGuardedRegion, KeLeaveGuardedRegion};
+
+/// The IRQL at which threads normally run, and at which all APCs can be delivered.
+pub const PASSIVE_LEVEL: KIRQL = 0;
+/// The IRQL at which APCs run, and which masks the delivery of APCs to the current thread.
+pub const APC_LEVEL: KIRQL = 1;
+/// The IRQL at which DPCs and the thread dispatcher run. Code running at this IRQL cannot block
+/// and cannot access paged memory.
+pub const DISPATCH_LEVEL: KIRQL = 2;
+/// The highest IRQL, which masks all interrupts.
+pub const HIGH_LEVEL: KIRQL = 15;
+
+/// Returns the current IRQL of the processor.
+#[inline]
+pub fn current_irql() -> KIRQL {
+    unsafe {
+        KeGetCurrentIrql()
+    }
+}
+
+/// Asserts that the current IRQL satisfies the given requirement in debug builds, e.g.
+/// `debug_assert_irql!(<= APC_LEVEL)` or `debug_assert_irql!(== DISPATCH_LEVEL)`. The IRQL levels
+/// are resolved from the [`irql`] module.
+///
+/// [`irql`]: crate::irql
+#[macro_export]
+macro_rules! debug_assert_irql {
+    ($op:tt $irql:ident) => {
+        if cfg!(debug_assertions) {
+            let current = $crate::irql::current_irql();
+
+            assert!(
+                current $op $crate::irql::$irql,
+                "the current IRQL {} does not satisfy IRQL {} {}",
+                current,
+                stringify!($op),
+                stringify!($irql),
+            );
+        }
+    };
+}
+
+/// Raises the IRQL of the current processor to the given IRQL, which must be greater than or equal
+/// to the current IRQL.
+///
+/// Returns an RAII guard which restores the previous IRQL when dropped.
+///
+/// # Panics
+///
+/// Panics if the given IRQL is lower than the current IRQL, as calling `KeRaiseIrql` with a lower
+/// IRQL results in a bug check.
+pub fn raise_irql(new_irql: KIRQL) -> IrqlGuard {
+    assert!(
+        new_irql >= current_irql(),
+        "cannot raise the IRQL to a lower IRQL",
+    );
+
+    let mut old_irql: KIRQL = 0;
+
+    unsafe {
+        KeRaiseIrql(
+            new_irql,
+            &mut old_irql,
+        )
+    };
+
+    IrqlGuard {
+        old_irql,
+        _marker: PhantomData,
+    }
+}
+
+/// Raises the IRQL of the current processor to `DISPATCH_LEVEL`, which disables thread
+/// scheduling on the current processor, e.g. to access per-processor data.
+#[inline]
+pub fn raise_irql_to_dpc_level() -> IrqlGuard {
+    raise_irql(DISPATCH_LEVEL)
+}
+
+/// An RAII structure that restores the previous IRQL when dropped. The IRQL belongs to the
+/// current processor, hence the guard cannot be sent to another thread.
+///
+/// This structure is created by the [`raise_irql`] and [`raise_irql_to_dpc_level`] functions.
+pub struct IrqlGuard {
+    old_irql: KIRQL,
+    _marker: PhantomData<*const ()>,
+}
+
+impl IrqlGuard {
+    /// Returns the IRQL that will be restored when the guard gets dropped.
+    #[inline]
+    pub fn old_irql(&self) -> KIRQL {
+        self.old_irql
+    }
+
+    /// Restores the previous IRQL. This is the same as dropping the guard.
+    #[inline]
+    pub fn lower(self) {}
+}
+
+impl Drop for IrqlGuard {
+    fn drop(&mut self) {
+        unsafe {
+            KeLowerIrql(
+                self.old_irql,
+            )
+        };
+    }
+}
+
+/// Enters a critical region, which disables the delivery of normal kernel APCs to the current
+/// thread, e.g. to prevent the thread from being suspended while it holds a lock. Special kernel
+/// APCs are still delivered.
+///
+/// Returns an RAII guard which leaves the critical region when dropped. Critical regions can be
+/// nested.
+pub fn enter_critical_region() -> CriticalRegion {
+    unsafe {
+        KeEnterCriticalRegion()
+    };
+
+    CriticalRegion {
+        _marker: PhantomData,
+    }
+}
+
+/// An RAII structure that leaves the critical region when dropped.
+///
+/// This structure is created by the [`enter_critical_region`] function.
+pub struct CriticalRegion {
+    _marker: PhantomData<*const ()>,
+}
+
+impl Drop for CriticalRegion {
+    fn drop(&mut self) {
+        unsafe {
+            KeLeaveCriticalRegion()
+        };
+    }
+}
+
+/// Enters a guarded region, which disables the delivery of all kernel APCs to the current thread,
+/// including special kernel APCs.
+///
+/// Returns an RAII guard which leaves the guarded region when dropped. Guarded regions can be
+/// nested.
+pub fn enter_guarded_region() -> GuardedRegion {
+    unsafe {
+        KeEnterGuardedRegion()
+    };
+
+    GuardedRegion {
+        _marker: PhantomData,
+    }
+}
+
+/// An RAII structure that leaves the guarded region when dropped.
+///
+/// This structure is created by the [`enter_guarded_region`] function.
+pub struct GuardedRegion {
+    _marker: PhantomData<*const ()>,
+}
+
+impl Drop for GuardedRegion {
+    fn drop(&mut self) {
+        unsafe {
+            KeLeaveGuardedRegion()
+        };
+    }
+}
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// A zero-sized token that shows that the current processor is running at a certain IRQL. A
+/// function that takes a token as argument can only be called at that IRQL, e.g.:
+///
+/// ```ignore
+/// fn read_config(_irql: &impl AtMostApc) { ... }
+///
+/// if let Some(passive) = Passive::new() {
+///     read_config(&passive);
+/// }
+/// ```
+///
+/// Tokens are checked when they are created, and cannot be sent to other threads. Raising the
+/// IRQL through [`Passive::raise_to_dpc_level`] or [`Apc::raise_to_dpc_level`] borrows the token,
+/// such that the token cannot be used while the IRQL is raised. Other functions that raise the
+/// IRQL, such as [`raise_irql`] or acquiring a lock, do not borrow a token. Tokens document the
+/// IRQL requirements of a function and catch mistakes at compile time, but unsafe code must not
+/// rely on them for soundness.
+pub trait IrqlToken: private::Sealed {
+    /// The IRQL that this token stands for.
+    const IRQL: KIRQL;
+}
+
+/// Implemented by the tokens that show that the current IRQL is at most `APC_LEVEL`, i.e.
+/// [`Passive`] and [`Apc`].
+pub trait AtMostApc: IrqlToken {}
+
+/// Implemented by the tokens that show that the current IRQL is at most `DISPATCH_LEVEL`, i.e.
+/// [`Passive`], [`Apc`] and [`Dispatch`].
+pub trait AtMostDispatch: IrqlToken {}
+
+macro_rules! irql_token {
+    ($(#[$meta:meta])* $name:ident, $irql:ident) => {
+        $(#[$meta])*
+        pub struct $name {
+            _marker: PhantomData<*const ()>,
+        }
+
+        impl $name {
+            /// Returns a token if the current IRQL matches the IRQL of the token, or `None`
+            /// otherwise.
+            pub fn new() -> Option<Self> {
+                match current_irql() == $irql {
+                    true => Some(Self {
+                        _marker: PhantomData,
+                    }),
+                    _ => None,
+                }
+            }
+
+            /// Returns a token without checking the current IRQL in release builds, for code
+            /// that knows the IRQL it runs at.
+            ///
+            /// # Safety
+            ///
+            /// The current IRQL must match the IRQL of the token, and must not change for as
+            /// long as the token is used.
+            #[inline]
+            pub unsafe fn new_unchecked() -> Self {
+                debug_assert_irql!(== $irql);
+
+                Self {
+                    _marker: PhantomData,
+                }
+            }
+        }
+
+        impl private::Sealed for $name {}
+
+        impl IrqlToken for $name {
+            const IRQL: KIRQL = $irql;
+        }
+    };
+}
+
+irql_token!(
+    /// A token that shows that the current IRQL is `PASSIVE_LEVEL`.
+    Passive, PASSIVE_LEVEL
+);
+
+irql_token!(
+    /// A token that shows that the current IRQL is `APC_LEVEL`.
+    Apc, APC_LEVEL
+);
+
+irql_token!(
+    /// A token that shows that the current IRQL is `DISPATCH_LEVEL`.
+    Dispatch, DISPATCH_LEVEL
+);
+
+impl AtMostApc for Passive {}
+impl AtMostApc for Apc {}
+impl AtMostDispatch for Passive {}
+impl AtMostDispatch for Apc {}
+impl AtMostDispatch for Dispatch {}
+
+impl Passive {
+    /// Raises the IRQL to `DISPATCH_LEVEL`, returning a guard that provides the [`Dispatch`]
+    /// token until it gets dropped.
+    pub fn raise_to_dpc_level(&mut self) -> DispatchGuard<'_> {
+        DispatchGuard::new()
+    }
+}
+
+impl Apc {
+    /// Raises the IRQL to `DISPATCH_LEVEL`, returning a guard that provides the [`Dispatch`]
+    /// token until it gets dropped.
+    pub fn raise_to_dpc_level(&mut self) -> DispatchGuard<'_> {
+        DispatchGuard::new()
+    }
+}
+
+/// An RAII structure that restores the previous IRQL when dropped, and that dereferences to the
+/// [`Dispatch`] token in the meantime. The token the IRQL was raised from stays borrowed until
+/// the guard gets dropped.
+///
+/// This structure is created by the [`Passive::raise_to_dpc_level`] and
+/// [`Apc::raise_to_dpc_level`] methods.
+pub struct DispatchGuard<'a> {
+    token: Dispatch,
+    _guard: IrqlGuard,
+    _marker: PhantomData<&'a mut ()>,
+}
+
+impl<'a> DispatchGuard<'a> {
+    fn new() -> Self {
+        let guard = raise_irql_to_dpc_level();
+
+        Self {
+            token: Dispatch {
+                _marker: PhantomData,
+            },
+            _guard: guard,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'a> Deref for DispatchGuard<'a> {
+    type Target = Dispatch;
+
+    fn deref(&self) -> &Dispatch {
+        &self.token
+    }
+}
@@ -1,34 +1,53 @@
 #![no_std]
 
 #![feature(alloc_error_handler)]
+#![feature(associated_type_defaults)]
 
 extern crate alloc;
 
 pub mod affinity;
 pub mod allocator;
 pub mod device;
+pub mod device_interface;
+pub mod dpc;
 pub mod driver;
 pub mod error;
+#[cfg(feature = "async")]
+pub mod executor;
+pub mod guid;
 pub mod intrin;
 pub mod io;
 pub mod ioctl;
+pub mod irql;
 pub mod mdl;
 pub mod memory;
+pub mod pod;
 pub mod process;
+pub mod process_notify;
 pub mod request;
 pub mod section;
 pub mod string;
 pub mod symbolic_link;
 pub mod sync;
+pub mod thread;
+pub mod time;
+pub mod timer;
 pub mod user_ptr;
 pub mod version;
+pub mod workitem;
 
 pub use crate::affinity::{get_cpu_count, get_current_cpu_num, run_on_cpu, run_on_each_cpu};
-pub use crate::device::{Access, Completion, Device, DeviceDoFlags, DeviceFlags, DeviceOperations, DeviceType, dispatch_device, RequestError};
+pub use crate::device::{Access, Completion, Device, DeviceBuilder, DeviceDoFlags, DeviceFlags, DeviceOperations, DeviceType};
+pub use crate::device::{dispatch_device, LowerDevice, RequestError, Serialized, SerializedDeviceOperations};
+pub use crate::device_interface::DeviceInterface;
 pub use crate::driver::Driver;
 pub use crate::error::Error;
+pub use crate::guid::Guid;
 pub use crate::ioctl::{ControlCode, RequiredAccess, TransferMethod};
-pub use crate::request::{IoRequest, IoControlRequest, ReadRequest, WriteRequest};
+pub use crate::pod::{Pod, PodError, Reader, Writer};
+pub use crate::request::{CancelSafeQueue, CompletionAction, FlushRequest, InternalIoControlRequest, IoRequest, IoControlRequest};
+pub use crate::request::{PendingRequest, PendingToken, PnpRequest, PowerRequest, QueryInformationRequest, QueryVolumeInformationRequest};
+pub use crate::request::{ReadRequest, Request, SetInformationRequest, ShutdownRequest, WriteRequest};
 pub use crate::symbolic_link::SymbolicLink;
 pub use crate::user_ptr::UserPtr;
 
@@ -65,10 +84,14 @@ macro_rules! kernel_module {
             driver: &mut $crate::DRIVER_OBJECT,
             registry_path: &$crate::UNICODE_STRING,
         ) -> $crate::NTSTATUS {
+            $crate::allocator::init();
+
             unsafe {
+                $crate::driver::init(driver);
+
                 driver.DriverUnload = Some(driver_exit);
 
-                for i in 0..$crate::IRP_MJ_MAXIMUM_FUNCTION {
+                for i in 0..=$crate::IRP_MJ_MAXIMUM_FUNCTION {
                     driver.MajorFunction[i as usize] = Some($crate::dispatch_device);
                 }
             }
@@ -107,6 +130,11 @@ macro_rules! kernel_module {
                 Some(mut m) => m.cleanup(driver),
                 _ => (),
             }
+
+            // Wait for any work items and callbacks that are still running code of the driver, now
+            // that the module has been dropped. System threads keep the driver object referenced
+            // instead.
+            $crate::sync::DRIVER_RUNDOWN.wait();
         }
     };
 }
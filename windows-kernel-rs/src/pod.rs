@@ -0,0 +1,2 @@
+pub use windows_kernel_common::pod::*;
+pub use windows_kernel_derive::Pod;
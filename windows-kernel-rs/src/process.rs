@@ -1,5 +1,6 @@
 use bitflags::bitflags;
 use crate::error::{Error, IntoResult};
+use crate::irql::AtMostApc;
 use windows_kernel_sys::base::{CLIENT_ID, HANDLE, KAPC_STATE, OBJECT_ATTRIBUTES, PEPROCESS};
 use windows_kernel_sys::ntoskrnl::{KeStackAttachProcess, KeUnstackDetachProcess};
 use windows_kernel_sys::ntoskrnl::{ObDereferenceObject, ObReferenceObject};
@@ -52,9 +53,9 @@ impl Process {
         handle as _
     }
 
-    pub fn attach(&self) -> ProcessAttachment {
+    pub fn attach(&self, irql: &impl AtMostApc) -> ProcessAttachment {
         unsafe {
-            ProcessAttachment::attach(self.process)
+            ProcessAttachment::attach(self.process, irql)
         }
     }
 }
@@ -73,7 +74,9 @@ pub struct ProcessAttachment {
 }
 
 impl ProcessAttachment {
-    pub unsafe fn attach(process: PEPROCESS) -> Self {
+    pub unsafe fn attach(process: PEPROCESS, _irql: &impl AtMostApc) -> Self {
+        crate::debug_assert_irql!(<= APC_LEVEL);
+
         let mut state: KAPC_STATE = core::mem::zeroed();
     
         ObReferenceObject(process as _);
@@ -0,0 +1,263 @@
+//! This module provides notifications about the creation and exit of processes, through
+//! `PsSetCreateProcessNotifyRoutineEx` and `PsSetCreateProcessNotifyRoutineEx2`. The closure can
+//! deny the creation of a process by returning an error.
+
+use alloc::boxed::Box;
+use core::sync::atomic::{AtomicPtr, Ordering};
+use crate::error::{Error, IntoResult};
+use crate::process::{Process, ProcessId};
+use crate::thread::ThreadId;
+use widestring::U16Str;
+use windows_kernel_sys::base::{HANDLE, PEPROCESS, PS_CREATE_NOTIFY_INFO, PVOID, UNICODE_STRING};
+use windows_kernel_sys::base::_PSCREATEPROCESSNOTIFYTYPE as PSCREATEPROCESSNOTIFYTYPE;
+use windows_kernel_sys::ntoskrnl::{ObReferenceObject, PsSetCreateProcessNotifyRoutineEx};
+use windows_kernel_sys::ntoskrnl::PsSetCreateProcessNotifyRoutineEx2;
+
+/// The `IsSubsystemProcess` bit in the flags of `PS_CREATE_NOTIFY_INFO`.
+const IS_SUBSYSTEM_PROCESS: u32 = 0x2;
+
+type Callback = dyn Fn(&Process, ProcessNotification<'_>) -> Result<(), Error> + Send + Sync;
+
+// The notify routines do not take a context, hence the closures are stored globally, one for
+// each of the notify routines.
+static CALLBACK: AtomicPtr<Box<Callback>> = AtomicPtr::new(core::ptr::null_mut());
+static CALLBACK_WITH_SUBSYSTEMS: AtomicPtr<Box<Callback>> =
+    AtomicPtr::new(core::ptr::null_mut());
+
+/// Information about a process that is being created.
+pub struct ProcessCreateInfo<'a> {
+    info: &'a PS_CREATE_NOTIFY_INFO,
+}
+
+impl<'a> ProcessCreateInfo<'a> {
+    /// Returns the ID of the parent process of the process. This is not necessarily the process
+    /// that created the process.
+    pub fn parent_process_id(&self) -> ProcessId {
+        self.info.ParentProcessId as _
+    }
+
+    /// Returns the ID of the process that created the process.
+    pub fn creating_process_id(&self) -> ProcessId {
+        self.info.CreatingThreadId.UniqueProcess as _
+    }
+
+    /// Returns the ID of the thread that created the process.
+    pub fn creating_thread_id(&self) -> ThreadId {
+        self.info.CreatingThreadId.UniqueThread as _
+    }
+
+    /// Returns the file name of the executable image of the process, if available.
+    pub fn image_file_name(&self) -> Option<&'a U16Str> {
+        unsafe { unicode_str(self.info.ImageFileName) }
+    }
+
+    /// Returns the command line of the process, if available.
+    pub fn command_line(&self) -> Option<&'a U16Str> {
+        unsafe { unicode_str(self.info.CommandLine) }
+    }
+
+    /// Returns whether the process is a subsystem process, such as a process of the Windows
+    /// Subsystem for Linux. Subsystem processes are only reported to a [`ProcessNotify`] that got
+    /// registered through [`ProcessNotify::register_with_subsystems`].
+    pub fn is_subsystem_process(&self) -> bool {
+        unsafe { self.info.__bindgen_anon_1.Flags } & IS_SUBSYSTEM_PROCESS != 0
+    }
+}
+
+/// Borrows the buffer of the `UNICODE_STRING`, if any.
+unsafe fn unicode_str<'a>(string: *const UNICODE_STRING) -> Option<&'a U16Str> {
+    let string = string.as_ref()?;
+
+    if string.Buffer.is_null() {
+        return None;
+    }
+
+    let buffer = core::slice::from_raw_parts(string.Buffer, (string.Length / 2) as usize);
+
+    Some(U16Str::from_slice(buffer))
+}
+
+/// A notification about a process, which is passed to the closure of a [`ProcessNotify`].
+pub enum ProcessNotification<'a> {
+    /// The process is being created. Returning an error from the closure denies the creation of
+    /// the process, and the error is returned to the creator of the process.
+    Create(ProcessCreateInfo<'a>),
+    /// The process exited, i.e. the last thread of the process terminated. The error returned
+    /// from the closure is ignored.
+    Exit,
+}
+
+/// This is the notify routine for [`ProcessNotify::register`].
+unsafe extern "C" fn notify_routine(
+    process: PEPROCESS,
+    _process_id: HANDLE,
+    info: *mut PS_CREATE_NOTIFY_INFO,
+) {
+    notify(&CALLBACK, process, info);
+}
+
+/// This is the notify routine for [`ProcessNotify::register_with_subsystems`].
+unsafe extern "C" fn notify_routine_with_subsystems(
+    process: PEPROCESS,
+    _process_id: HANDLE,
+    info: *mut PS_CREATE_NOTIFY_INFO,
+) {
+    notify(&CALLBACK_WITH_SUBSYSTEMS, process, info);
+}
+
+unsafe fn notify(
+    callback: &AtomicPtr<Box<Callback>>,
+    process: PEPROCESS,
+    info: *mut PS_CREATE_NOTIFY_INFO,
+) {
+    let f = callback.load(Ordering::Acquire);
+
+    if f.is_null() {
+        return;
+    }
+
+    let f = &*f;
+
+    // The process is only borrowed for the duration of the notification, while `Process`
+    // dereferences the process when dropped.
+    ObReferenceObject(process as _);
+
+    let process = Process {
+        process,
+    };
+
+    match info.as_mut() {
+        Some(info) => {
+            let result = f(&process, ProcessNotification::Create(ProcessCreateInfo {
+                info: &*info,
+            }));
+
+            // A success or informational status would let the process be created anyway.
+            if let Err(error) = result {
+                info.CreationStatus = match error.to_ntstatus() {
+                    status if status >= 0 => Error::UNSUCCESSFUL.to_ntstatus(),
+                    status => status,
+                };
+            }
+        }
+        _ => {
+            let _ = f(&process, ProcessNotification::Exit);
+        }
+    }
+}
+
+/// A registration of a closure that gets notified whenever a process is created or exits. The
+/// closure runs at `PASSIVE_LEVEL` in the context of the thread that creates the process, or of
+/// the last thread of the process that exits.
+///
+/// Only one registration through [`ProcessNotify::register`], and one through
+/// [`ProcessNotify::register_with_subsystems`], can exist at a time. The driver must be linked
+/// with `/INTEGRITYCHECK`, which `windows-kernel-build` takes care of.
+///
+/// Dropping the registration unregisters the closure, and waits for any notifications that are
+/// running the closure to finish, which requires `IRQL == PASSIVE_LEVEL`.
+///
+/// ```ignore
+/// let notify = ProcessNotify::register(|process, notification| {
+///     if let ProcessNotification::Create(info) = notification {
+///         if info.image_file_name().is_some_and(|name| is_blocked(name)) {
+///             return Err(Error::ACCESS_DENIED);
+///         }
+///     }
+///
+///     Ok(())
+/// })?;
+/// ```
+pub struct ProcessNotify {
+    with_subsystems: bool,
+}
+
+impl ProcessNotify {
+    /// Registers the closure through `PsSetCreateProcessNotifyRoutineEx`, such that it gets
+    /// notified about the creation and exit of Win32 processes. Returns
+    /// [`Error::INVALID_PARAMETER`] if such a registration already exists.
+    pub fn register<F>(f: F) -> Result<Self, Error>
+    where
+        F: Fn(&Process, ProcessNotification<'_>) -> Result<(), Error> + Send + Sync + 'static,
+    {
+        Self::register_inner(Box::new(f), false)
+    }
+
+    /// Registers the closure through `PsSetCreateProcessNotifyRoutineEx2`, such that it gets
+    /// notified about the creation and exit of both Win32 processes and subsystem processes.
+    /// Returns [`Error::INVALID_PARAMETER`] if such a registration already exists.
+    pub fn register_with_subsystems<F>(f: F) -> Result<Self, Error>
+    where
+        F: Fn(&Process, ProcessNotification<'_>) -> Result<(), Error> + Send + Sync + 'static,
+    {
+        Self::register_inner(Box::new(f), true)
+    }
+
+    fn register_inner(f: Box<Callback>, with_subsystems: bool) -> Result<Self, Error> {
+        crate::debug_assert_irql!(== PASSIVE_LEVEL);
+
+        let callback = callback(with_subsystems);
+        let f = Box::into_raw(Box::new(f));
+
+        // Store the closure before registering the notify routine, such that it is available
+        // to the first notification.
+        if callback.compare_exchange(
+            core::ptr::null_mut(),
+            f,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ).is_err() {
+            drop(unsafe { Box::from_raw(f) });
+            return Err(Error::INVALID_PARAMETER);
+        }
+
+        if let Err(error) = unsafe { set_notify_routine(with_subsystems, false) } {
+            callback.store(core::ptr::null_mut(), Ordering::Release);
+            drop(unsafe { Box::from_raw(f) });
+            return Err(error);
+        }
+
+        Ok(Self {
+            with_subsystems,
+        })
+    }
+}
+
+impl Drop for ProcessNotify {
+    fn drop(&mut self) {
+        crate::debug_assert_irql!(== PASSIVE_LEVEL);
+
+        // Removing the notify routine waits for any notifications that are still running, hence
+        // nobody uses the closure afterwards. Should the notify routine still be registered, then
+        // it may still run the closure, which is leaked instead.
+        if unsafe { set_notify_routine(self.with_subsystems, true) }.is_err() {
+            return;
+        }
+
+        let f = callback(self.with_subsystems).swap(core::ptr::null_mut(), Ordering::AcqRel);
+
+        drop(unsafe { Box::from_raw(f) });
+    }
+}
+
+fn callback(with_subsystems: bool) -> &'static AtomicPtr<Box<Callback>> {
+    match with_subsystems {
+        true => &CALLBACK_WITH_SUBSYSTEMS,
+        _ => &CALLBACK,
+    }
+}
+
+/// Registers or removes the notify routine for the registration.
+unsafe fn set_notify_routine(with_subsystems: bool, remove: bool) -> Result<(), Error> {
+    match with_subsystems {
+        true => PsSetCreateProcessNotifyRoutineEx2(
+            PSCREATEPROCESSNOTIFYTYPE::PsCreateProcessNotifySubsystems,
+            notify_routine_with_subsystems as PVOID,
+            remove as _,
+        ),
+        _ => PsSetCreateProcessNotifyRoutineEx(
+            Some(notify_routine),
+            remove as _,
+        ),
+    }.into_result()
+}
@@ -1,11 +1,27 @@
+use alloc::boxed::Box;
 use bitflags::bitflags;
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use core::mem::ManuallyDrop;
 use core::ops::Deref;
-use crate::error::Error;
+use crate::device::{release_file_context, Completion};
+use crate::error::{Error, IntoResult};
 use crate::ioctl::{ControlCode, RequiredAccess, TransferMethod};
+use crate::mdl::AccessMode;
+use crate::sync::rundown::{RundownGuard, DRIVER_RUNDOWN};
 use crate::user_ptr::UserPtr;
 use windows_kernel_sys::base::{IO_NO_INCREMENT, IO_STACK_LOCATION, IRP, STATUS_SUCCESS};
+use windows_kernel_sys::base::{IRP_MJ_CREATE, IRP_MJ_POWER, SL_PENDING_RETURNED, STATUS_MORE_PROCESSING_REQUIRED};
+use windows_kernel_sys::base::{DEVICE_OBJECT, IO_CSQ, KIRQL, KSPIN_LOCK, LIST_ENTRY, NTSTATUS, PVOID};
+use windows_kernel_sys::base::{FILE_INFORMATION_CLASS, FS_INFORMATION_CLASS, POWER_STATE, POWER_STATE_TYPE};
 use windows_kernel_sys::base::_MM_PAGE_PRIORITY as MM_PAGE_PRIORITY;
-use windows_kernel_sys::ntoskrnl::{IoCompleteRequest, IoGetCurrentIrpStackLocation};
+use windows_kernel_sys::base::_MODE as MODE;
+use windows_kernel_sys::ntoskrnl::{IoCompleteRequest, IoGetCurrentIrpStackLocation, IoMarkIrpPending};
+use windows_kernel_sys::ntoskrnl::{IoCallDriver, IoSetCompletionRoutineEx, IoSkipCurrentIrpStackLocation};
+use windows_kernel_sys::ntoskrnl::IoCopyCurrentIrpStackLocationToNext;
+use windows_kernel_sys::ntoskrnl::{PoCallDriver, PoStartNextPowerIrp};
+use windows_kernel_sys::ntoskrnl::{IoCsqInitialize, IoCsqInsertIrp, IoCsqRemoveNextIrp};
+use windows_kernel_sys::ntoskrnl::{KeAcquireSpinLock, KeInitializeSpinLock, KeReleaseSpinLock};
 use windows_kernel_sys::ntoskrnl::{MmGetMdlByteCount, MmGetMdlByteOffset, MmGetSystemAddressForMdlSafe};
 
 bitflags! {
@@ -30,6 +46,23 @@ bitflags! {
     }
 }
 
+/// A closure that is called once a forwarded request has been completed by the lower device. The
+/// closure receives the request and its result, and decides how the completion of the request
+/// proceeds.
+pub type CompletionRoutine = Box<dyn FnOnce(IoRequest, Result<u32, Error>) -> CompletionAction + Send>;
+
+/// Returned by a [`CompletionRoutine`] to decide how the completion of the request proceeds.
+pub enum CompletionAction {
+    /// Continues the completion of the request with the status set by the lower device.
+    Continue(IoRequest),
+    /// Replaces the status of the request with the given size or error, and continues the
+    /// completion of the request.
+    ContinueWith(Result<u32, Error>, IoRequest),
+    /// Stops the completion of the request, by returning `STATUS_MORE_PROCESSING_REQUIRED`. The
+    /// request has been turned into a [`PendingRequest`] that will be completed later on.
+    MoreProcessingRequired(PendingToken),
+}
+
 pub struct IoRequest {
     irp: *mut IRP,    
 }
@@ -60,9 +93,114 @@ impl IoRequest {
         self.stack_location().MajorFunction
     }
 
-    pub(crate) fn complete(&self, value: Result<u32, Error>) {
+    pub fn minor(&self) -> u8 {
+        self.stack_location().MinorFunction
+    }
+
+    /// Returns whether the request originates from user mode or from kernel mode. Buffers of
+    /// requests that originate from user mode must be probed before they can be accessed.
+    pub fn requestor_mode(&self) -> AccessMode {
+        if self.irp().RequestorMode == MODE::KernelMode as _ {
+            AccessMode::KernelMode
+        } else {
+            AccessMode::UserMode
+        }
+    }
+
+    pub(crate) fn status(&self) -> Result<u32, Error> {
+        let irp = self.irp();
+        let status = unsafe { irp.IoStatus.__bindgen_anon_1.Status };
+
+        if status >= 0 {
+            Ok(irp.IoStatus.Information as _)
+        } else {
+            Err(Error::from_ntstatus(status))
+        }
+    }
+
+    /// Uses [`IoSkipCurrentIrpStackLocation`] to pass the current stack location on to the lower
+    /// device as is.
+    pub(crate) fn skip_current_stack_location(&self) {
+        unsafe {
+            IoSkipCurrentIrpStackLocation(self.irp_mut());
+        }
+    }
+
+    /// Returns whether the request has been marked as pending through [`IoMarkIrpPending`].
+    pub(crate) fn is_pending(&self) -> bool {
+        self.stack_location().Control as u32 & SL_PENDING_RETURNED != 0
+    }
+
+    /// Uses [`IoMarkIrpPending`] to mark the request as pending.
+    pub(crate) fn mark_pending(&self) {
+        unsafe {
+            IoMarkIrpPending(self.irp_mut());
+        }
+    }
+
+    /// Uses [`IoCopyCurrentIrpStackLocationToNext`] to copy the current stack location to the
+    /// next stack location, such that a completion routine can be set for the lower device.
+    pub(crate) fn copy_current_stack_location_to_next(&self) {
+        unsafe {
+            IoCopyCurrentIrpStackLocationToNext(self.irp_mut());
+        }
+    }
+
+    /// Uses [`IoSetCompletionRoutineEx`] to call the given closure once the lower device completes
+    /// the request. The I/O manager keeps a reference to the given device object until the
+    /// completion routine has returned, such that the driver cannot be unloaded while the
+    /// completion routine is still running.
+    pub(crate) fn set_completion_routine(
+        &self,
+        device: *mut DEVICE_OBJECT,
+        routine: CompletionRoutine,
+    ) -> Result<(), Error> {
+        let context = Box::into_raw(Box::new(routine));
+
+        let result = unsafe {
+            IoSetCompletionRoutineEx(
+                device,
+                self.irp_mut(),
+                Some(completion_routine),
+                context as _,
+                true as _,
+                true as _,
+                true as _,
+            )
+        }.into_result();
+
+        if result.is_err() {
+            drop(unsafe { Box::from_raw(context) });
+        }
+
+        result
+    }
+
+    /// Uses [`IoCallDriver`] to pass the request on to the given device.
+    pub(crate) fn call_driver(self, device: *mut DEVICE_OBJECT) -> NTSTATUS {
+        unsafe {
+            IoCallDriver(device, self.irp_mut())
+        }
+    }
+
+    /// Uses [`PoCallDriver`] to pass the power request on to the given device.
+    pub(crate) fn call_power_driver(self, device: *mut DEVICE_OBJECT) -> NTSTATUS {
+        unsafe {
+            PoCallDriver(device, self.irp_mut())
+        }
+    }
+
+    /// Uses [`PoStartNextPowerIrp`] to signal that the driver is ready for the next power
+    /// request, which must be done before a power request is passed on or completed.
+    pub(crate) fn start_next_power_irp(&self) {
+        unsafe {
+            PoStartNextPowerIrp(self.irp_mut());
+        }
+    }
+
+    pub(crate) fn set_status(&self, value: Result<u32, Error>) {
         let irp = self.irp_mut();
-        
+
         match value {
             Ok(value) => {
                 irp.IoStatus.Information = value as _;
@@ -73,27 +211,209 @@ impl IoRequest {
                 irp.IoStatus.__bindgen_anon_1.Status = error.to_ntstatus();
             }
         }
+    }
+
+    pub(crate) fn complete(&self, value: Result<u32, Error>) {
+        // The file context of a create request that fails is released here, rather than in the
+        // dispatch routine, as the request may have been pended.
+        if value.is_err() && self.major() as u32 == IRP_MJ_CREATE {
+            unsafe {
+                release_file_context(self);
+            }
+        }
+
+        if self.major() as u32 == IRP_MJ_POWER {
+            self.start_next_power_irp();
+        }
+
+        self.set_status(value);
 
         unsafe {
-            IoCompleteRequest(irp, IO_NO_INCREMENT as _);
+            IoCompleteRequest(self.irp_mut(), IO_NO_INCREMENT as _);
         }
     }
 }
 
-pub struct ReadRequest {
-    pub(crate) inner: IoRequest,
+unsafe extern "C" fn completion_routine(
+    _device: *mut DEVICE_OBJECT,
+    irp: *mut IRP,
+    context: PVOID,
+) -> NTSTATUS {
+    // The dispatch routine marked the request as pending, hence the pending flag does not have to
+    // be propagated up the stack, and the closure is free to stop the completion of the request.
+    let routine = Box::from_raw(context as *mut CompletionRoutine);
+    let request = IoRequest::from_raw(irp);
+    let status = request.status();
+
+    match routine(request, status) {
+        // STATUS_CONTINUE_COMPLETION
+        CompletionAction::Continue(_) => STATUS_SUCCESS,
+        CompletionAction::ContinueWith(value, request) => {
+            request.set_status(value);
+            STATUS_SUCCESS
+        }
+        CompletionAction::MoreProcessingRequired(_) => STATUS_MORE_PROCESSING_REQUIRED,
+    }
 }
 
-impl Deref for ReadRequest {
-    type Target = IoRequest;
+/// Implemented by [`IoRequest`] and the typed requests that wrap it, such that code that holds on
+/// to requests, like [`PendingRequest`], can work with any of them.
+pub trait Request: Sized {
+    /// Returns the underlying [`IoRequest`].
+    fn as_io_request(&self) -> &IoRequest;
+
+    /// Consumes the typed request, returning the underlying [`IoRequest`].
+    fn into_io_request(self) -> IoRequest;
+
+    /// Wraps the given [`IoRequest`] in the typed request.
+    ///
+    /// The caller must guarantee that the major function of the request matches the typed
+    /// request.
+    unsafe fn from_io_request(request: IoRequest) -> Self;
+}
+
+impl Request for IoRequest {
+    fn as_io_request(&self) -> &IoRequest {
+        self
+    }
+
+    fn into_io_request(self) -> IoRequest {
+        self
+    }
+
+    unsafe fn from_io_request(request: IoRequest) -> Self {
+        request
+    }
+}
+
+/// Proof that the request being dispatched has been marked as pending, which is required to
+/// return [`Completion::Pending`]. It can only be obtained by creating a [`PendingRequest`], or by
+/// inserting the request into a [`CancelSafeQueue`].
+///
+/// [`Completion::Pending`]: crate::device::Completion::Pending
+#[must_use]
+pub struct PendingToken {
+    _private: (),
+}
+
+/// A request that has been marked as pending, such that it can be completed at a later point in
+/// time, possibly from another thread.
+///
+/// The request is completed exactly once: either through [`complete`], or with
+/// [`Error::CANCELLED`] when the [`PendingRequest`] is dropped without having been completed.
+/// After marking a request as pending, the dispatch routine must return [`Completion::Pending`]
+/// with the [`PendingToken`] returned by [`new`].
+///
+/// ```ignore
+/// let (request, token) = PendingRequest::new(request);
+/// self.requests.lock().push(request);
+///
+/// Ok(Completion::Pending(token))
+/// ```
+///
+/// A pending request holds run-time protection of [`DRIVER_RUNDOWN`], such that the driver does
+/// not get unloaded before the request has been completed.
+///
+/// [`complete`]: PendingRequest::complete
+/// [`new`]: PendingRequest::new
+/// [`Completion::Pending`]: crate::device::Completion::Pending
+pub struct PendingRequest<R: Request = IoRequest> {
+    request: Option<R>,
+    // This is only released after the request has been completed. Acquiring it only fails once
+    // the module has been dropped, by which time no requests are being dispatched.
+    _rundown: Option<RundownGuard<'static>>,
+}
+
+unsafe impl<R: Request> Send for PendingRequest<R> {}
+
+impl<R: Request> PendingRequest<R> {
+    /// Uses [`IoMarkIrpPending`] to mark the request as pending. Returns the pending request
+    /// along with the [`PendingToken`] to return from the dispatch routine.
+    pub fn new(request: R) -> (Self, PendingToken) {
+        request.as_io_request().mark_pending();
+
+        let request = Self {
+            request: Some(request),
+            _rundown: DRIVER_RUNDOWN.acquire(),
+        };
+
+        (request, PendingToken { _private: () })
+    }
+
+    /// Wraps a request that has already been marked as pending.
+    pub(crate) unsafe fn from_pending(request: R) -> Self {
+        Self {
+            request: Some(request),
+            _rundown: DRIVER_RUNDOWN.acquire(),
+        }
+    }
+
+    /// Completes the request with the given size or error.
+    pub fn complete(mut self, value: Result<u32, Error>) {
+        if let Some(request) = self.request.take() {
+            request.as_io_request().complete(value);
+        }
+    }
+}
+
+impl<R: Request> Deref for PendingRequest<R> {
+    type Target = R;
 
     fn deref(&self) -> &Self::Target {
-        &self.inner
+        self.request.as_ref().unwrap()
+    }
+}
+
+impl<R: Request> Drop for PendingRequest<R> {
+    fn drop(&mut self) {
+        if let Some(request) = self.request.take() {
+            request.as_io_request().complete(Err(Error::CANCELLED));
+        }
     }
 }
 
+/// Implements [`Deref`], [`Into<IoRequest>`] and [`Request`] for a typed request that wraps an
+/// [`IoRequest`].
+macro_rules! impl_request {
+    ($name:ident) => {
+        impl Deref for $name {
+            type Target = IoRequest;
+
+            fn deref(&self) -> &Self::Target {
+                &self.inner
+            }
+        }
+
+        impl Into<IoRequest> for $name {
+            fn into(self) -> IoRequest {
+                self.inner
+            }
+        }
+
+        impl Request for $name {
+            fn as_io_request(&self) -> &IoRequest {
+                &self.inner
+            }
+
+            fn into_io_request(self) -> IoRequest {
+                self.inner
+            }
+
+            unsafe fn from_io_request(request: IoRequest) -> Self {
+                Self { inner: request }
+            }
+        }
+    };
+}
+
+pub struct ReadRequest {
+    pub(crate) inner: IoRequest,
+}
+
+impl_request!(ReadRequest);
+
 impl ReadRequest {
-    pub fn user_ptr(&self) -> UserPtr {
+    pub fn user_ptr(&self) -> UserPtr<'_> {
         let stack_location = self.stack_location();
         let irp = self.irp();
 
@@ -111,7 +431,9 @@ impl ReadRequest {
 
             (ptr, size)
         } else {
-            (core::ptr::null_mut(), 0)
+            let size = unsafe { stack_location.Parameters.Read }.Length as usize;
+
+            return neither_user_ptr(self, core::ptr::null(), irp.UserBuffer, 0, size);
         };
 
         unsafe { UserPtr::new_buffered(ptr, 0, size) }
@@ -131,26 +453,14 @@ impl ReadRequest {
     }
 }
 
-impl Into<IoRequest> for ReadRequest {
-    fn into(self) -> IoRequest {
-        self.inner
-    }
-}
-
 pub struct WriteRequest {
     pub(crate) inner: IoRequest,
 }
 
-impl Deref for WriteRequest {
-    type Target = IoRequest;
-
-    fn deref(&self) -> &Self::Target {
-        &self.inner
-    }
-}
+impl_request!(WriteRequest);
 
 impl WriteRequest {
-    pub fn user_ptr(&self) -> UserPtr {
+    pub fn user_ptr(&self) -> UserPtr<'_> {
         let stack_location = self.stack_location();
         let irp = self.irp();
 
@@ -168,7 +478,9 @@ impl WriteRequest {
 
             (ptr, size)
         } else {
-            (core::ptr::null_mut(), 0)
+            let size = unsafe { stack_location.Parameters.Write }.Length as usize;
+
+            return neither_user_ptr(self, irp.UserBuffer, core::ptr::null_mut(), size, 0);
         };
 
         unsafe { UserPtr::new_buffered(ptr, size, 0) }
@@ -188,25 +500,105 @@ impl WriteRequest {
     }
 }
 
-impl Into<IoRequest> for WriteRequest {
-    fn into(self) -> IoRequest {
-        self.inner
+pub struct IoControlRequest {
+    pub(crate) inner: IoRequest,
+}
+
+impl_request!(IoControlRequest);
+
+impl IoControlRequest {
+    pub fn control_code(&self) -> ControlCode {
+        let stack_location = self.stack_location();
+
+        unsafe {
+            stack_location.Parameters.DeviceIoControl.IoControlCode.into()
+        }
+    }
+
+    pub fn function(&self) -> (RequiredAccess, u32) {
+        let code = self.control_code();
+
+        (code.required_access(), code.number())
+    }
+
+    pub fn user_ptr(&self) -> UserPtr<'_> {
+        device_control_user_ptr(&self.inner, self.control_code())
     }
 }
 
-pub struct IoControlRequest {
-    pub(crate) inner: IoRequest,
+fn device_control_user_ptr(request: &IoRequest, code: ControlCode) -> UserPtr<'_> {
+    let stack_location = request.stack_location();
+    let irp = request.irp();
+
+    let system_buffer = unsafe { irp.AssociatedIrp.SystemBuffer };
+
+    let mdl_address = if !irp.MdlAddress.is_null() {
+        unsafe { MmGetSystemAddressForMdlSafe(irp.MdlAddress, MM_PAGE_PRIORITY::HighPagePriority as _) }
+    } else {
+        core::ptr::null_mut()
+    };
+
+    let input_size = unsafe {
+        stack_location.Parameters.DeviceIoControl.InputBufferLength
+    } as usize;
+    let output_size = unsafe {
+        stack_location.Parameters.DeviceIoControl.OutputBufferLength
+    } as usize;
+
+    match code.transfer_method() {
+        TransferMethod::Buffered =>
+            unsafe { UserPtr::new_buffered(system_buffer, input_size, output_size) },
+        TransferMethod::InputDirect =>
+            unsafe { UserPtr::new_direct(mdl_address, system_buffer, output_size, input_size) },
+        TransferMethod::OutputDirect =>
+            unsafe { UserPtr::new_direct(system_buffer, mdl_address, input_size, output_size) },
+        TransferMethod::Neither => {
+            let input_ptr = unsafe {
+                stack_location.Parameters.DeviceIoControl.Type3InputBuffer
+            };
+
+            neither_user_ptr(request, input_ptr, irp.UserBuffer, input_size, output_size)
+        }
+    }
 }
 
-impl Deref for IoControlRequest {
-    type Target = IoRequest;
+/// Creates a [`UserPtr::Neither`] for the buffers of the request. The addresses of the buffers are
+/// only valid in the context of the requesting thread, hence the buffers are empty once the
+/// request has been marked as pending, as it may then be completed from any thread.
+fn neither_user_ptr(
+    request: &IoRequest,
+    read_ptr: *const cty::c_void,
+    write_ptr: *mut cty::c_void,
+    read_size: usize,
+    write_size: usize,
+) -> UserPtr<'_> {
+    if request.is_pending() {
+        return unsafe {
+            UserPtr::new_neither(
+                core::ptr::null(),
+                core::ptr::null_mut(),
+                0,
+                0,
+                request.requestor_mode(),
+            )
+        };
+    }
 
-    fn deref(&self) -> &Self::Target {
-        &self.inner
+    unsafe {
+        UserPtr::new_neither(read_ptr, write_ptr, read_size, write_size, request.requestor_mode())
     }
 }
 
-impl IoControlRequest {
+/// A request for `IRP_MJ_INTERNAL_DEVICE_CONTROL`, which is used by other drivers to send I/O
+/// control codes to the device. Unlike [`IoControlRequest`], these requests cannot be sent from
+/// user mode.
+pub struct InternalIoControlRequest {
+    pub(crate) inner: IoRequest,
+}
+
+impl_request!(InternalIoControlRequest);
+
+impl InternalIoControlRequest {
     pub fn control_code(&self) -> ControlCode {
         let stack_location = self.stack_location();
 
@@ -221,40 +613,312 @@ impl IoControlRequest {
         (code.required_access(), code.number())
     }
 
-    pub fn user_ptr(&self) -> UserPtr {
-        let stack_location = self.stack_location();
-        let irp = self.irp();
+    pub fn user_ptr(&self) -> UserPtr<'_> {
+        device_control_user_ptr(&self.inner, self.control_code())
+    }
 
-        let system_buffer = unsafe { irp.AssociatedIrp.SystemBuffer };
+    /// Returns the input buffer for I/O control codes that use [`TransferMethod::Neither`]. As the
+    /// request originates from kernel mode, drivers often use this to pass a pointer to a
+    /// structure.
+    pub fn input_buffer(&self) -> *mut cty::c_void {
+        unsafe {
+            self.stack_location().Parameters.DeviceIoControl.Type3InputBuffer
+        }
+    }
 
-        let mdl_address = if !irp.MdlAddress.is_null() {
-            unsafe { MmGetSystemAddressForMdlSafe(irp.MdlAddress, MM_PAGE_PRIORITY::HighPagePriority as _) }
-        } else {
-            core::ptr::null_mut()
+    /// Returns the output buffer for I/O control codes that use [`TransferMethod::Neither`].
+    pub fn output_buffer(&self) -> *mut cty::c_void {
+        self.irp().UserBuffer
+    }
+}
+
+/// A request for `IRP_MJ_FLUSH_BUFFERS`, e.g. as the result of `FlushFileBuffers`.
+pub struct FlushRequest {
+    pub(crate) inner: IoRequest,
+}
+
+impl_request!(FlushRequest);
+
+/// A request for `IRP_MJ_QUERY_INFORMATION`, e.g. as the result of `GetFileInformationByHandle`.
+/// The information is written to the buffer returned by [`user_ptr`], after which the request is
+/// completed with the number of bytes written.
+///
+/// [`user_ptr`]: QueryInformationRequest::user_ptr
+pub struct QueryInformationRequest {
+    pub(crate) inner: IoRequest,
+}
+
+impl_request!(QueryInformationRequest);
+
+impl QueryInformationRequest {
+    pub fn information_class(&self) -> FILE_INFORMATION_CLASS {
+        unsafe { self.stack_location().Parameters.QueryFile.FileInformationClass }
+    }
+
+    pub fn user_ptr(&self) -> UserPtr<'_> {
+        let size = unsafe { self.stack_location().Parameters.QueryFile.Length } as usize;
+
+        unsafe { UserPtr::new_buffered(self.irp().AssociatedIrp.SystemBuffer, 0, size) }
+    }
+}
+
+/// A request for `IRP_MJ_SET_INFORMATION`, e.g. as the result of `SetFileInformationByHandle`.
+/// The information can be read from the buffer returned by [`user_ptr`].
+///
+/// [`user_ptr`]: SetInformationRequest::user_ptr
+pub struct SetInformationRequest {
+    pub(crate) inner: IoRequest,
+}
+
+impl_request!(SetInformationRequest);
+
+impl SetInformationRequest {
+    pub fn information_class(&self) -> FILE_INFORMATION_CLASS {
+        unsafe { self.stack_location().Parameters.SetFile.FileInformationClass }
+    }
+
+    pub fn user_ptr(&self) -> UserPtr<'_> {
+        let size = unsafe { self.stack_location().Parameters.SetFile.Length } as usize;
+
+        unsafe { UserPtr::new_buffered(self.irp().AssociatedIrp.SystemBuffer, size, 0) }
+    }
+}
+
+/// A request for `IRP_MJ_QUERY_VOLUME_INFORMATION`, e.g. as the result of
+/// `GetVolumeInformationByHandleW`. The information is written to the buffer returned by
+/// [`user_ptr`], after which the request is completed with the number of bytes written.
+///
+/// [`user_ptr`]: QueryVolumeInformationRequest::user_ptr
+pub struct QueryVolumeInformationRequest {
+    pub(crate) inner: IoRequest,
+}
+
+impl_request!(QueryVolumeInformationRequest);
+
+impl QueryVolumeInformationRequest {
+    pub fn information_class(&self) -> FS_INFORMATION_CLASS {
+        unsafe { self.stack_location().Parameters.QueryVolume.FsInformationClass }
+    }
+
+    pub fn user_ptr(&self) -> UserPtr<'_> {
+        let size = unsafe { self.stack_location().Parameters.QueryVolume.Length } as usize;
+
+        unsafe { UserPtr::new_buffered(self.irp().AssociatedIrp.SystemBuffer, 0, size) }
+    }
+}
+
+/// A request for `IRP_MJ_SHUTDOWN`. This is only sent to devices that have been registered
+/// through `IoRegisterShutdownNotification`.
+pub struct ShutdownRequest {
+    pub(crate) inner: IoRequest,
+}
+
+impl_request!(ShutdownRequest);
+
+/// A request for `IRP_MJ_PNP`. The minor function (`IRP_MN_*`) describes the actual request.
+pub struct PnpRequest {
+    pub(crate) inner: IoRequest,
+}
+
+impl_request!(PnpRequest);
+
+/// A request for `IRP_MJ_POWER`. The minor function (`IRP_MN_*`) describes the actual request.
+pub struct PowerRequest {
+    pub(crate) inner: IoRequest,
+}
+
+impl_request!(PowerRequest);
+
+impl PowerRequest {
+    pub fn state_type(&self) -> POWER_STATE_TYPE {
+        unsafe { self.stack_location().Parameters.Power.Type }
+    }
+
+    pub fn state(&self) -> POWER_STATE {
+        unsafe { self.stack_location().Parameters.Power.State }
+    }
+}
+
+#[repr(C)]
+struct CancelSafeQueueInner {
+    // This must be the first field, as the callbacks cast the `IO_CSQ` back to this structure.
+    csq: IO_CSQ,
+    lock: KSPIN_LOCK,
+    head: LIST_ENTRY,
+}
+
+/// A queue of pending requests built on top of the cancel-safe IRP queue (`IoCsq`) API.
+///
+/// The queue is protected by a spin lock and takes care of cancellation: requests that are
+/// cancelled while they are in the queue are removed from the queue and automatically completed
+/// with [`Error::CANCELLED`]. Requests that are still in the queue when it is dropped are also
+/// completed with [`Error::CANCELLED`].
+pub struct CancelSafeQueue<T: Request> {
+    inner: Box<UnsafeCell<CancelSafeQueueInner>>,
+    _marker: PhantomData<T>,
+}
+
+unsafe impl<T: Request> Send for CancelSafeQueue<T> {}
+unsafe impl<T: Request> Sync for CancelSafeQueue<T> {}
+
+impl<T: Request> CancelSafeQueue<T> {
+    /// Uses [`IoCsqInitialize`] to set up a new empty queue.
+    pub fn new() -> Result<Self, Error> {
+        let inner: Box<UnsafeCell<CancelSafeQueueInner>> = Box::new(unsafe {
+            core::mem::zeroed()
+        });
+
+        let raw = inner.get();
+
+        unsafe {
+            (*raw).head.Flink = &mut (*raw).head;
+            (*raw).head.Blink = &mut (*raw).head;
+
+            KeInitializeSpinLock(&mut (*raw).lock);
+
+            IoCsqInitialize(
+                &mut (*raw).csq,
+                Some(csq_insert_irp),
+                Some(csq_remove_irp),
+                Some(csq_peek_next_irp::<T>),
+                Some(csq_acquire_lock),
+                Some(csq_release_lock),
+                Some(csq_complete_canceled_irp),
+            )
+        }.into_result()?;
+
+        Ok(Self {
+            inner,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Uses [`IoCsqInsertIrp`] to mark the request as pending and to insert it into the queue. The
+    /// dispatch routine must return the [`Completion`] returned by this function.
+    pub fn insert(&self, request: T) -> Completion {
+        let irp = request.into_io_request().irp_mut() as *mut IRP;
+
+        unsafe {
+            IoCsqInsertIrp(&mut (*self.inner.get()).csq, irp, core::ptr::null_mut());
+        }
+
+        // `IoCsqInsertIrp` marks the request as pending.
+        Completion::Pending(PendingToken { _private: () })
+    }
+
+    /// Uses [`IoCsqRemoveNextIrp`] to remove the oldest request from the queue.
+    pub fn remove_next(&self) -> Option<PendingRequest<T>> {
+        self.remove_next_with(core::ptr::null_mut())
+    }
+
+    /// Uses [`IoCsqRemoveNextIrp`] to remove the oldest request from the queue for which the given
+    /// predicate returns `true`. The predicate is called with the spin lock of the queue held.
+    pub fn remove_next_if<F>(&self, mut predicate: F) -> Option<PendingRequest<T>>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let mut predicate: &mut dyn FnMut(&T) -> bool = &mut predicate;
+
+        self.remove_next_with(&mut predicate as *mut _ as PVOID)
+    }
+
+    fn remove_next_with(&self, peek_context: PVOID) -> Option<PendingRequest<T>> {
+        let irp = unsafe {
+            IoCsqRemoveNextIrp(&mut (*self.inner.get()).csq, peek_context)
         };
 
-        let input_size = unsafe {
-            stack_location.Parameters.DeviceIoControl.InputBufferLength
-        } as usize;
-        let output_size = unsafe {
-            stack_location.Parameters.DeviceIoControl.OutputBufferLength
-        } as usize;
-
-        match self.control_code().transfer_method() {
-            TransferMethod::Buffered =>
-                unsafe { UserPtr::new_buffered(system_buffer, input_size, output_size) },
-            TransferMethod::InputDirect =>
-                unsafe { UserPtr::new_direct(mdl_address, system_buffer, output_size, input_size) },
-            TransferMethod::OutputDirect =>
-                unsafe { UserPtr::new_direct(system_buffer, mdl_address, input_size, output_size) },
-            TransferMethod::Neither =>
-                unsafe { UserPtr::new_neither() },
+        if irp.is_null() {
+            return None;
         }
+
+        Some(unsafe {
+            PendingRequest::from_pending(T::from_io_request(IoRequest::from_raw(irp)))
+        })
     }
 }
 
-impl Into<IoRequest> for IoControlRequest {
-    fn into(self) -> IoRequest {
-        self.inner
+impl<T: Request> Drop for CancelSafeQueue<T> {
+    fn drop(&mut self) {
+        // Dropping the pending requests completes them with `Error::CANCELLED`.
+        while self.remove_next().is_some() {}
     }
 }
+
+fn irp_list_entry(irp: *mut IRP) -> *mut LIST_ENTRY {
+    unsafe { &mut (*irp).Tail.Overlay.__bindgen_anon_2.ListEntry }
+}
+
+fn irp_from_list_entry(entry: *mut LIST_ENTRY) -> *mut IRP {
+    let offset = core::mem::offset_of!(IRP, Tail.Overlay.__bindgen_anon_2.ListEntry);
+
+    (entry as usize - offset) as *mut IRP
+}
+
+unsafe fn csq_inner(csq: *mut IO_CSQ) -> *mut CancelSafeQueueInner {
+    csq as *mut CancelSafeQueueInner
+}
+
+unsafe extern "C" fn csq_insert_irp(csq: *mut IO_CSQ, irp: *mut IRP) {
+    let head = &mut (*csq_inner(csq)).head as *mut LIST_ENTRY;
+    let entry = irp_list_entry(irp);
+    let tail = (*head).Blink;
+
+    (*entry).Flink = head;
+    (*entry).Blink = tail;
+    (*tail).Flink = entry;
+    (*head).Blink = entry;
+}
+
+unsafe extern "C" fn csq_remove_irp(_csq: *mut IO_CSQ, irp: *mut IRP) {
+    let entry = irp_list_entry(irp);
+    let next = (*entry).Flink;
+    let prev = (*entry).Blink;
+
+    (*prev).Flink = next;
+    (*next).Blink = prev;
+}
+
+unsafe extern "C" fn csq_peek_next_irp<T: Request>(
+    csq: *mut IO_CSQ,
+    irp: *mut IRP,
+    peek_context: PVOID,
+) -> *mut IRP {
+    let head = &mut (*csq_inner(csq)).head as *mut LIST_ENTRY;
+
+    let mut entry = if irp.is_null() {
+        (*head).Flink
+    } else {
+        (*irp_list_entry(irp)).Flink
+    };
+
+    while entry != head {
+        let next_irp = irp_from_list_entry(entry);
+
+        if peek_context.is_null() {
+            return next_irp;
+        }
+
+        let predicate = &mut *(peek_context as *mut &mut dyn FnMut(&T) -> bool);
+        let request = ManuallyDrop::new(T::from_io_request(IoRequest::from_raw(next_irp)));
+
+        if predicate(&request) {
+            return next_irp;
+        }
+
+        entry = (*entry).Flink;
+    }
+
+    core::ptr::null_mut()
+}
+
+unsafe extern "C" fn csq_acquire_lock(csq: *mut IO_CSQ, irql: *mut KIRQL) {
+    KeAcquireSpinLock(&mut (*csq_inner(csq)).lock, irql);
+}
+
+unsafe extern "C" fn csq_release_lock(csq: *mut IO_CSQ, irql: KIRQL) {
+    KeReleaseSpinLock(&mut (*csq_inner(csq)).lock, irql);
+}
+
+unsafe extern "C" fn csq_complete_canceled_irp(_csq: *mut IO_CSQ, irp: *mut IRP) {
+    IoRequest::from_raw(irp).complete(Err(Error::CANCELLED));
+}
@@ -1,5 +1,6 @@
 use bitflags::bitflags;
 use crate::error::{Error, IntoResult};
+use crate::irql::Passive;
 use crate::process::ZwProcess;
 use crate::string::create_unicode_string;
 use widestring::U16CString;
@@ -57,7 +58,14 @@ unsafe impl Send for Section {}
 unsafe impl Sync for Section {}
 
 impl Section {
-    pub fn open(path: &str, obj_flags: ObjectFlags, access: SectionAccess) -> Result<Self, Error> {
+    pub fn open(
+        path: &str,
+        obj_flags: ObjectFlags,
+        access: SectionAccess,
+        _irql: &Passive,
+    ) -> Result<Self, Error> {
+        crate::debug_assert_irql!(== PASSIVE_LEVEL);
+
         let name = U16CString::from_str(path).unwrap();
         let mut name = create_unicode_string(name.as_slice());
 
@@ -0,0 +1,158 @@
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use core::time::Duration;
+use crate::sync::event::Event;
+use crate::sync::fast_mutex::{FastMutex, FastMutexGuard};
+use crate::sync::push_lock::{PushLock, PushLockReadGuard, PushLockWriteGuard};
+use crate::sync::spin_lock::SpinLock;
+use crate::sync::wait::Waitable;
+
+/// A guard of a lock that a [`Condvar`] can temporarily release while waiting.
+pub trait LockGuard<'a>: Sized {
+    type Lock: ?Sized + 'a;
+
+    /// Releases the lock, returning a reference to it.
+    fn unlock(self) -> &'a Self::Lock;
+
+    /// Acquires the lock again.
+    fn relock(lock: &'a Self::Lock) -> Self;
+}
+
+impl<'a, T: ?Sized> LockGuard<'a> for FastMutexGuard<'a, T> {
+    type Lock = FastMutex<T>;
+
+    fn unlock(self) -> &'a FastMutex<T> {
+        self.lock
+    }
+
+    fn relock(lock: &'a FastMutex<T>) -> Self {
+        // The mutex was locked at `IRQL <= APC_LEVEL` before.
+        lock.acquire()
+    }
+}
+
+impl<'a, T: ?Sized> LockGuard<'a> for PushLockReadGuard<'a, T> {
+    type Lock = PushLock<T>;
+
+    fn unlock(self) -> &'a PushLock<T> {
+        self.lock
+    }
+
+    fn relock(lock: &'a PushLock<T>) -> Self {
+        // The lock was locked at `IRQL <= APC_LEVEL` before.
+        lock.acquire_read()
+    }
+}
+
+impl<'a, T: ?Sized> LockGuard<'a> for PushLockWriteGuard<'a, T> {
+    type Lock = PushLock<T>;
+
+    fn unlock(self) -> &'a PushLock<T> {
+        self.lock
+    }
+
+    fn relock(lock: &'a PushLock<T>) -> Self {
+        // The lock was locked at `IRQL <= APC_LEVEL` before.
+        lock.acquire_write()
+    }
+}
+
+/// A condition variable, which lets threads block until some condition on the data protected by
+/// a [`FastMutex`] or [`PushLock`] becomes true, e.g. to implement producer/consumer patterns.
+///
+/// ```ignore
+/// let mut queue = mutex.lock(&passive);
+///
+/// while queue.is_empty() {
+///     queue = condvar.wait(queue);
+/// }
+/// ```
+///
+/// Every waiting thread waits on its own synchronization [`Event`], such that notifications
+/// cannot get lost between releasing the lock and starting to wait. Waiting is only allowed at
+/// `IRQL <= APC_LEVEL`, while the notify methods can be called at `IRQL <= DISPATCH_LEVEL`.
+pub struct Condvar {
+    waiters: SpinLock<VecDeque<Arc<Event>>>,
+}
+
+impl Condvar {
+    /// Creates a new condition variable without any waiting threads.
+    pub const fn new() -> Self {
+        Self {
+            waiters: SpinLock::new(VecDeque::new()),
+        }
+    }
+
+    fn enqueue(&self) -> Arc<Event> {
+        let event = Arc::new(Event::synchronization());
+
+        self.waiters.lock().push_back(event.clone());
+
+        event
+    }
+
+    /// Releases the lock, blocks the current thread until the condition variable gets notified,
+    /// and then acquires the lock again. Spurious wake-ups are possible, hence the condition
+    /// should be checked in a loop.
+    pub fn wait<'a, G: LockGuard<'a>>(&self, guard: G) -> G {
+        // Enqueue the event before releasing the lock, such that a notification that happens
+        // right after releasing the lock is not lost.
+        let event = self.enqueue();
+        let lock = guard.unlock();
+
+        event.wait();
+
+        G::relock(lock)
+    }
+
+    /// Like [`wait`], but blocks for no longer than the timeout. Returns the guard and whether
+    /// the timeout expired without the condition variable being notified.
+    ///
+    /// [`wait`]: Condvar::wait
+    pub fn wait_timeout<'a, G: LockGuard<'a>>(&self, guard: G, timeout: Duration) -> (G, bool) {
+        let event = self.enqueue();
+        let lock = guard.unlock();
+
+        let timed_out = match event.wait_timeout(timeout) {
+            Ok(()) => false,
+            _ => {
+                // Remove the event, unless it got dequeued by a notification in the meantime.
+                let mut waiters = self.waiters.lock();
+
+                match waiters.iter().position(|waiter| Arc::ptr_eq(waiter, &event)) {
+                    Some(index) => {
+                        waiters.remove(index);
+                        true
+                    }
+                    _ => false,
+                }
+            }
+        };
+
+        (G::relock(lock), timed_out)
+    }
+
+    /// Wakes up one of the threads that is waiting, if any.
+    pub fn notify_one(&self) {
+        let event = self.waiters.lock().pop_front();
+
+        if let Some(event) = event {
+            event.set();
+        }
+    }
+
+    /// Wakes up all of the threads that are waiting.
+    pub fn notify_all(&self) {
+        let waiters = core::mem::take(&mut *self.waiters.lock());
+
+        for event in waiters {
+            event.set();
+        }
+    }
+}
+
+impl Default for Condvar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
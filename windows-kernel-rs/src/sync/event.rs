@@ -0,0 +1,112 @@
+use alloc::boxed::Box;
+use core::cell::UnsafeCell;
+use crate::sync::wait::Waitable;
+use windows_kernel_sys::base::{KEVENT, PVOID};
+use windows_kernel_sys::base::_EVENT_TYPE as EVENT_TYPE;
+use windows_kernel_sys::ntoskrnl::{KeClearEvent, KeInitializeEvent, KeReadStateEvent, KeSetEvent};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EventType {
+    /// The event wakes up all of the waiting threads, and stays signaled until it is reset.
+    Notification,
+    /// The event wakes up a single waiting thread, and is then reset automatically.
+    Synchronization,
+}
+
+impl Into<EVENT_TYPE::Type> for EventType {
+    fn into(self) -> EVENT_TYPE::Type {
+        match self {
+            EventType::Notification => EVENT_TYPE::NotificationEvent,
+            EventType::Synchronization => EVENT_TYPE::SynchronizationEvent,
+        }
+    }
+}
+
+/// An [`Event`] is a dispatcher object that lets threads wait until another part of the driver
+/// signals the event through [`set`]. Waiting is done through the [`Waitable`] trait.
+///
+/// ```ignore
+/// let event = Event::new(EventType::Notification, false);
+///
+/// // On another thread.
+/// event.set();
+///
+/// event.wait();
+/// ```
+///
+/// [`set`]: Event::set
+pub struct Event {
+    // The `KEVENT` contains a wait list that points to itself, hence it must not move.
+    raw: Box<UnsafeCell<KEVENT>>,
+}
+
+unsafe impl Send for Event {}
+unsafe impl Sync for Event {}
+
+impl Event {
+    /// Creates a new event of the given type, that is initially signaled if `signaled` is set.
+    pub fn new(event_type: EventType, signaled: bool) -> Self {
+        let raw: Box<UnsafeCell<KEVENT>> = Box::new(UnsafeCell::new(unsafe {
+            core::mem::zeroed()
+        }));
+
+        unsafe {
+            KeInitializeEvent(
+                raw.get(),
+                event_type.into(),
+                signaled as _,
+            )
+        };
+
+        Self {
+            raw,
+        }
+    }
+
+    /// Creates a new notification event that is not signaled.
+    pub fn notification() -> Self {
+        Self::new(EventType::Notification, false)
+    }
+
+    /// Creates a new synchronization event that is not signaled.
+    pub fn synchronization() -> Self {
+        Self::new(EventType::Synchronization, false)
+    }
+
+    /// Signals the event, and returns whether the event was signaled before. This can be called at
+    /// `IRQL <= DISPATCH_LEVEL`.
+    pub fn set(&self) -> bool {
+        unsafe {
+            KeSetEvent(
+                self.raw.get(),
+                0,
+                false as _,
+            ) != 0
+        }
+    }
+
+    /// Resets the event to the non-signaled state. This can be called at
+    /// `IRQL <= DISPATCH_LEVEL`.
+    pub fn reset(&self) {
+        unsafe {
+            KeClearEvent(
+                self.raw.get(),
+            )
+        };
+    }
+
+    /// Returns whether the event is currently signaled.
+    pub fn is_set(&self) -> bool {
+        unsafe {
+            KeReadStateEvent(
+                self.raw.get(),
+            ) != 0
+        }
+    }
+}
+
+unsafe impl Waitable for Event {
+    fn as_dispatcher_object(&self) -> PVOID {
+        self.raw.get() as _
+    }
+}
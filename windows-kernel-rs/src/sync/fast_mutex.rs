@@ -1,6 +1,8 @@
 use alloc::boxed::Box;
 use core::cell::UnsafeCell;
+use core::marker::PhantomData;
 use core::ops::{Deref, DerefMut};
+use crate::irql::AtMostApc;
 use windows_kernel_sys::base::FAST_MUTEX;
 use windows_kernel_sys::ntoskrnl::{
     ExInitializeFastMutex, ExAcquireFastMutex, ExReleaseFastMutex, ExTryToAcquireFastMutex,
@@ -18,23 +20,24 @@ use windows_kernel_sys::ntoskrnl::{
 /// [`lock`]: FastMutex::lock
 /// [`try_lock`]: FastMutex::try_lock
 pub struct FastMutex<T: ?Sized> {
-    pub(crate) lock: Box<FAST_MUTEX>,
+    pub(crate) lock: Box<UnsafeCell<FAST_MUTEX>>,
     pub(crate) data: UnsafeCell<T>,
 }
 
-unsafe impl<T> Send for FastMutex<T> {}
-unsafe impl<T> Sync for FastMutex<T> {}
+unsafe impl<T: ?Sized + Send> Send for FastMutex<T> {}
+unsafe impl<T: ?Sized + Send> Sync for FastMutex<T> {}
 
 impl<T> FastMutex<T> {
     /// Creates a new mutex in an unlocked state ready for use.
     pub fn new(data: T) -> Self {
-        let mut lock: Box<FAST_MUTEX> = Box::new(unsafe {
+        // The `FAST_MUTEX` contains a wait list that points to itself, hence it must not move.
+        let lock: Box<UnsafeCell<FAST_MUTEX>> = Box::new(UnsafeCell::new(unsafe {
             core::mem::zeroed()
-        });
+        }));
 
         unsafe {
             ExInitializeFastMutex(
-                &mut *lock,
+                lock.get(),
             )
         };
 
@@ -50,25 +53,37 @@ impl<T> FastMutex<T> {
         let Self { data, .. } = self;
         data.into_inner()
     }
+}
+
+impl<T: ?Sized> FastMutex<T> {
+    /// Returns a mutable reference to the underlying data. As this borrows the mutex mutably, no
+    /// locking is needed.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
 
     /// Attempts to acquire this lock.
     ///
     /// If the lock could not be acquired at this time, then `None` is returned. Otherwise, an RAII
     /// guard is returned. The lock will be unlocked when the guard is dropped.
     ///
-    /// This function does not block.
+    /// This function does not block. The token shows that the caller runs at
+    /// `IRQL <= APC_LEVEL`.
     #[inline]
-    pub fn try_lock(&mut self) -> Option<FastMutexGuard<T>> {
+    pub fn try_lock(&self, _irql: &impl AtMostApc) -> Option<FastMutexGuard<T>> {
+        crate::debug_assert_irql!(<= APC_LEVEL);
+
         let status = unsafe {
             ExTryToAcquireFastMutex(
-                &mut *self.lock,
+                self.lock.get(),
             )
         } != 0;
 
         match status {
             true => Some(FastMutexGuard {
-                lock: &mut self.lock,
-                data: unsafe { &mut *self.data.get() },
+                lock: self,
+                _marker: PhantomData,
             }),
             _ => None,
         }
@@ -82,19 +97,30 @@ impl<T> FastMutex<T> {
     /// unlocked.
     ///
     /// The underlying function does not allow for recursion. If the thread already holds the lock
-    /// and tries to lock the mutex again, this function will return `None` instead.
+    /// and tries to lock the mutex again, the thread deadlocks.
+    ///
+    /// Acquiring the mutex raises the IRQL to `APC_LEVEL`, hence the caller must be running at
+    /// `IRQL <= APC_LEVEL`, which the token shows.
     #[inline]
-    pub fn lock(&mut self) -> Option<FastMutexGuard<T>> {
+    pub fn lock(&self, _irql: &impl AtMostApc) -> FastMutexGuard<T> {
+        self.acquire()
+    }
+
+    /// Acquires the mutex without a token, for code that knows it runs at `IRQL <= APC_LEVEL`.
+    #[inline]
+    pub(crate) fn acquire(&self) -> FastMutexGuard<T> {
+        crate::debug_assert_irql!(<= APC_LEVEL);
+
         unsafe {
             ExAcquireFastMutex(
-                &mut *self.lock,
+                self.lock.get(),
             )
         };
 
-        Some(FastMutexGuard {
-            lock: &mut self.lock,
-            data: unsafe { &mut *self.data.get() },
-        })
+        FastMutexGuard {
+            lock: self,
+            _marker: PhantomData,
+        }
     }
 }
 
@@ -121,15 +147,15 @@ impl<T> From<T> for FastMutex<T> {
 /// [`lock`]: FastMutex::lock
 /// [`try_lock`]: FastMutex::try_lock
 pub struct FastMutexGuard<'a, T: 'a + ?Sized> {
-    pub(crate) lock: &'a mut FAST_MUTEX,
-    pub(crate) data: &'a mut T,
+    pub(crate) lock: &'a FastMutex<T>,
+    pub(crate) _marker: PhantomData<*const ()>,
 }
 
 impl<'a, T: ?Sized> Drop for FastMutexGuard<'a, T> {
     fn drop(&mut self) {
         unsafe {
             ExReleaseFastMutex(
-                &mut *self.lock,
+                self.lock.lock.get(),
             )
         };
     }
@@ -139,12 +165,12 @@ impl<'a, T: ?Sized> Deref for FastMutexGuard<'a, T> {
     type Target = T;
 
     fn deref(&self) -> &T {
-        self.data
+        unsafe { &*self.lock.data.get() }
     }
 }
 
 impl<'a, T: ?Sized> DerefMut for FastMutexGuard<'a, T> {
     fn deref_mut(&mut self) -> &mut T {
-        self.data
+        unsafe { &mut *self.lock.data.get() }
     }
 }
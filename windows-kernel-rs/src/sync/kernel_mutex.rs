@@ -0,0 +1,185 @@
+use alloc::boxed::Box;
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use core::ops::Deref;
+use core::time::Duration;
+use crate::error::Error;
+use crate::irql::AtMostApc;
+use crate::sync::wait::{wait_for_object, wait_result, Waitable};
+use crate::time::Timeout;
+use windows_kernel_sys::base::{KMUTEX, PVOID};
+use windows_kernel_sys::ntoskrnl::{KeInitializeMutex, KeReleaseMutex};
+
+/// A [`KernelMutex`] is a mutual exclusion primitive built on top of the `KMUTEX` dispatcher
+/// object. Unlike a [`FastMutex`], it can be acquired recursively by the thread that owns it.
+///
+/// As the mutex can be acquired recursively, the guards only provide shared access to the data.
+/// Use a type with interior mutability, such as [`Cell`] or [`RefCell`], to modify the data.
+///
+/// While a thread owns the mutex, the delivery of normal kernel APCs is disabled. The mutex can
+/// only be acquired at `IRQL <= APC_LEVEL`.
+///
+/// The mutex can also be passed to [`wait_any`] and [`wait_all`], in which case a successful wait
+/// acquires the mutex. Use [`make_guard_unchecked`] to release it again.
+///
+/// [`wait_any`]: crate::sync::wait::wait_any
+/// [`wait_all`]: crate::sync::wait::wait_all
+/// [`make_guard_unchecked`]: KernelMutex::make_guard_unchecked
+/// [`FastMutex`]: crate::sync::fast_mutex::FastMutex
+/// [`Cell`]: core::cell::Cell
+/// [`RefCell`]: core::cell::RefCell
+pub struct KernelMutex<T: ?Sized> {
+    // The `KMUTEX` contains a wait list that points to itself, hence it must not move.
+    pub(crate) lock: Box<UnsafeCell<KMUTEX>>,
+    pub(crate) data: T,
+}
+
+unsafe impl<T: ?Sized + Send> Send for KernelMutex<T> {}
+unsafe impl<T: ?Sized + Send> Sync for KernelMutex<T> {}
+
+impl<T> KernelMutex<T> {
+    /// Creates a new mutex in an unlocked state ready for use.
+    pub fn new(data: T) -> Self {
+        let lock: Box<UnsafeCell<KMUTEX>> = Box::new(UnsafeCell::new(unsafe {
+            core::mem::zeroed()
+        }));
+
+        unsafe {
+            KeInitializeMutex(
+                lock.get(),
+                0,
+            )
+        };
+
+        Self {
+            lock,
+            data,
+        }
+    }
+
+    /// Consumes this [`KernelMutex`], returning the underlying data.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.data
+    }
+}
+
+impl<T: ?Sized> KernelMutex<T> {
+    /// Returns a mutable reference to the underlying data. As this borrows the mutex mutably, no
+    /// locking is needed.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.data
+    }
+
+    /// Acquires the mutex, blocking the current thread until it is able to do so. If the current
+    /// thread already owns the mutex, this returns immediately.
+    ///
+    /// Returns an RAII guard which releases the mutex when dropped. The token shows that the
+    /// caller runs at `IRQL <= APC_LEVEL`.
+    #[inline]
+    pub fn lock(&self, _irql: &impl AtMostApc) -> KernelMutexGuard<T> {
+        crate::debug_assert_irql!(<= APC_LEVEL);
+
+        let status = unsafe { wait_for_object(self.lock.get() as _, None) };
+
+        // Without a timeout the wait only returns once the mutex is owned, which may be after its
+        // previous owner abandoned it. Any other status means the mutex is not owned, and
+        // releasing it would bugcheck.
+        assert!(wait_result(status).is_ok(), "failed to acquire the kernel mutex");
+
+        KernelMutexGuard {
+            lock: self,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Acquires the mutex, blocking the current thread until it is able to do so or until the
+    /// timeout expires, in which case [`Error::IO_TIMEOUT`] is returned.
+    #[inline]
+    pub fn lock_timeout(
+        &self,
+        timeout: Duration,
+        _irql: &impl AtMostApc,
+    ) -> Result<KernelMutexGuard<T>, Error> {
+        crate::debug_assert_irql!(<= APC_LEVEL);
+
+        let timeout = Timeout::Relative(timeout);
+
+        wait_result(unsafe { wait_for_object(self.lock.get() as _, Some(timeout)) })?;
+
+        Ok(KernelMutexGuard {
+            lock: self,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Returns a guard for a mutex that the current thread acquired by waiting for it, e.g.
+    /// through [`wait_any`]. Dropping the guard releases the mutex once.
+    ///
+    /// # Safety
+    ///
+    /// The current thread must own the mutex through a wait that is not yet matched by a guard.
+    ///
+    /// [`wait_any`]: crate::sync::wait::wait_any
+    #[inline]
+    pub unsafe fn make_guard_unchecked(&self) -> KernelMutexGuard<T> {
+        KernelMutexGuard {
+            lock: self,
+            _marker: PhantomData,
+        }
+    }
+}
+
+// Waiting for the mutex only acquires ownership, which is not tracked by Rust: the guards merely
+// give shared access to the data.
+unsafe impl<T: ?Sized> Waitable for KernelMutex<T> {
+    fn as_dispatcher_object(&self) -> PVOID {
+        self.lock.get() as _
+    }
+}
+
+impl<T: ?Sized + Default> Default for KernelMutex<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T> From<T> for KernelMutex<T> {
+    fn from(data: T) -> Self {
+        Self::new(data)
+    }
+}
+
+/// An RAII implementation of a "scoped lock" of a kernel mutex. When this structure is dropped
+/// (falls out of scope), the mutex will be released once.
+///
+/// This structure is created by the [`lock`] and [`lock_timeout`] methods on [`KernelMutex`]. The
+/// mutex is owned by the thread that acquired it, hence the guard cannot be sent to another
+/// thread.
+///
+/// [`lock`]: KernelMutex::lock
+/// [`lock_timeout`]: KernelMutex::lock_timeout
+pub struct KernelMutexGuard<'a, T: 'a + ?Sized> {
+    lock: &'a KernelMutex<T>,
+    _marker: PhantomData<*const ()>,
+}
+
+impl<'a, T: ?Sized> Drop for KernelMutexGuard<'a, T> {
+    fn drop(&mut self) {
+        unsafe {
+            KeReleaseMutex(
+                self.lock.lock.get(),
+                false as _,
+            )
+        };
+    }
+}
+
+impl<'a, T: ?Sized> Deref for KernelMutexGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.lock.data
+    }
+}
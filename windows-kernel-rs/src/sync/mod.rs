@@ -1,5 +1,27 @@
+pub mod condvar;
+pub mod event;
 pub mod fast_mutex;
+pub mod kernel_mutex;
+pub mod once;
 pub mod push_lock;
+pub mod queued_spin_lock;
+pub mod resource;
+pub mod rundown;
+pub mod semaphore;
+pub mod slist;
+pub mod spin_lock;
+pub mod wait;
 
+pub use self::condvar::Condvar;
+pub use self::event::{Event, EventType};
 pub use self::fast_mutex::FastMutex as Mutex;
+pub use self::kernel_mutex::KernelMutex;
+pub use self::once::{Once, OnceLock};
 pub use self::push_lock::PushLock as RwLock;
+pub use self::queued_spin_lock::InStackQueuedSpinLock;
+pub use self::resource::Resource;
+pub use self::rundown::{Rundown, RundownGuard, DRIVER_RUNDOWN};
+pub use self::semaphore::Semaphore;
+pub use self::slist::SList;
+pub use self::spin_lock::SpinLock;
+pub use self::wait::{wait_all, wait_any, Waitable};
@@ -0,0 +1,176 @@
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicBool, Ordering};
+use windows_kernel_sys::base::EX_PUSH_LOCK;
+use windows_kernel_sys::ntoskrnl::{
+    ExAcquirePushLockExclusive,
+    ExReleasePushLockExclusive,
+    KeEnterCriticalRegion,
+    KeLeaveCriticalRegion,
+};
+
+/// A synchronization primitive which can be used to run a one-time initialization, e.g. of global
+/// driver state. Threads that call [`call_once`] while the initialization is running block on a
+/// push lock until it has finished, rather than spinning.
+///
+/// Running the initialization requires `IRQL <= APC_LEVEL`. Once the initialization has completed,
+/// [`call_once`] and [`is_completed`] can be called at any IRQL.
+///
+/// [`call_once`]: Once::call_once
+/// [`is_completed`]: Once::is_completed
+pub struct Once {
+    completed: AtomicBool,
+    // The push lock only holds a pointer to the wait blocks of waiters while it is locked, during
+    // which `self` is borrowed, hence the push lock does not have to be boxed.
+    lock: UnsafeCell<EX_PUSH_LOCK>,
+}
+
+unsafe impl Send for Once {}
+unsafe impl Sync for Once {}
+
+impl Once {
+    /// Creates a new [`Once`] that has not run yet.
+    pub const fn new() -> Self {
+        // This is what `ExInitializePushLock` does, which is not a `const fn`.
+        Self {
+            completed: AtomicBool::new(false),
+            lock: UnsafeCell::new(0),
+        }
+    }
+
+    /// Returns whether the initialization has completed.
+    #[inline]
+    pub fn is_completed(&self) -> bool {
+        self.completed.load(Ordering::Acquire)
+    }
+
+    /// Runs the closure if this is the first call to [`call_once`]. Otherwise, blocks the current
+    /// thread until the initialization that is currently running has completed. Once this
+    /// returns, the initialization has completed.
+    ///
+    /// Calling [`call_once`] on the same [`Once`] from within the closure deadlocks.
+    ///
+    /// [`call_once`]: Once::call_once
+    pub fn call_once<F>(&self, f: F)
+    where
+        F: FnOnce(),
+    {
+        if self.is_completed() {
+            return;
+        }
+
+        unsafe {
+            KeEnterCriticalRegion();
+            ExAcquirePushLockExclusive(self.lock.get());
+        }
+
+        if !self.completed.load(Ordering::Relaxed) {
+            f();
+            self.completed.store(true, Ordering::Release);
+        }
+
+        unsafe {
+            ExReleasePushLockExclusive(self.lock.get());
+            KeLeaveCriticalRegion();
+        }
+    }
+}
+
+impl Default for Once {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A cell which can be written to only once, e.g. to lazily initialize global driver state:
+///
+/// ```ignore
+/// static CONFIG: OnceLock<Config> = OnceLock::new();
+///
+/// let config = CONFIG.get_or_init(|| Config::load());
+/// ```
+///
+/// See [`Once`] for the IRQL requirements.
+pub struct OnceLock<T> {
+    once: Once,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+unsafe impl<T: Send> Send for OnceLock<T> {}
+unsafe impl<T: Send + Sync> Sync for OnceLock<T> {}
+
+impl<T> OnceLock<T> {
+    /// Creates a new empty cell.
+    pub const fn new() -> Self {
+        Self {
+            once: Once::new(),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    /// Returns the value, or `None` if the cell has not been initialized yet.
+    #[inline]
+    pub fn get(&self) -> Option<&T> {
+        match self.once.is_completed() {
+            true => Some(unsafe { (*self.value.get()).assume_init_ref() }),
+            _ => None,
+        }
+    }
+
+    /// Returns the value, initializing it with the closure if the cell has not been initialized
+    /// yet. Blocks the current thread if another thread is initializing the cell.
+    pub fn get_or_init<F>(&self, f: F) -> &T
+    where
+        F: FnOnce() -> T,
+    {
+        self.once.call_once(|| unsafe {
+            (*self.value.get()).write(f());
+        });
+
+        unsafe { (*self.value.get()).assume_init_ref() }
+    }
+
+    /// Initializes the cell with the value. Returns the value as an error if the cell has already
+    /// been initialized.
+    pub fn set(&self, value: T) -> Result<(), T> {
+        let mut value = Some(value);
+
+        self.get_or_init(|| value.take().unwrap());
+
+        match value {
+            Some(value) => Err(value),
+            _ => Ok(()),
+        }
+    }
+
+    /// Consumes the cell, returning the value if the cell has been initialized.
+    pub fn into_inner(mut self) -> Option<T> {
+        self.take()
+    }
+
+    /// Takes the value out of the cell, leaving the cell uninitialized. As this borrows the cell
+    /// mutably, no locking is needed.
+    pub fn take(&mut self) -> Option<T> {
+        match self.once.is_completed() {
+            true => {
+                self.once = Once::new();
+                Some(unsafe { self.value.get_mut().assume_init_read() })
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<T> Default for OnceLock<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for OnceLock<T> {
+    fn drop(&mut self) {
+        if self.once.is_completed() {
+            unsafe { self.value.get_mut().assume_init_drop() };
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use alloc::boxed::Box;
 use core::cell::UnsafeCell;
+use core::marker::PhantomData;
 use core::ops::{Deref, DerefMut};
+use crate::irql::AtMostApc;
 use windows_kernel_sys::base::EX_PUSH_LOCK;
 use windows_kernel_sys::ntoskrnl::{
     ExInitializePushLock,
@@ -8,6 +10,8 @@ use windows_kernel_sys::ntoskrnl::{
     ExReleasePushLockShared,
     ExAcquirePushLockExclusive,
     ExReleasePushLockExclusive,
+    ExTryAcquirePushLockShared,
+    ExTryAcquirePushLockExclusive,
     KeEnterCriticalRegion,
     KeLeaveCriticalRegion,
 };
@@ -34,23 +38,24 @@ use windows_kernel_sys::ntoskrnl::{
 /// [`PushLock`] for exclusive access, even in the event that the [`PushLock`] is acquired for
 /// shared access.
 ///
-/// [`FastMutex`]: crate::fast_mutex::FastMutex
+/// [`FastMutex`]: crate::sync::fast_mutex::FastMutex
 pub struct PushLock<T: ?Sized> {
-    pub(crate) lock: Box<EX_PUSH_LOCK>,
+    pub(crate) lock: Box<UnsafeCell<EX_PUSH_LOCK>>,
     pub(crate) data: UnsafeCell<T>,
 }
 
-unsafe impl<T> Send for PushLock<T> {}
-unsafe impl<T> Sync for PushLock<T> {}
+unsafe impl<T: ?Sized + Send> Send for PushLock<T> {}
+unsafe impl<T: ?Sized + Send + Sync> Sync for PushLock<T> {}
 
 impl<T> PushLock<T> {
     /// Creates new instance of [`PushLock<T>`] that is unlocked.
     pub fn new(data: T) -> Self {
-        let mut lock: Box<EX_PUSH_LOCK> = Box::new(0);
+        // Waiters link wait blocks on their stack to the push lock, hence it must not move.
+        let lock: Box<UnsafeCell<EX_PUSH_LOCK>> = Box::new(UnsafeCell::new(0));
 
         unsafe {
             ExInitializePushLock(
-                &mut *lock,
+                lock.get(),
             )
         };
 
@@ -66,6 +71,15 @@ impl<T> PushLock<T> {
         let Self { data, .. } = self;
         data.into_inner()
     }
+}
+
+impl<T: ?Sized> PushLock<T> {
+    /// Returns a mutable reference to the underlying data. As this borrows the [`PushLock`]
+    /// mutably, no locking is needed.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
 
     /// Locks this [`PushLock`] with shared read access, blocking the current thread until it can
     /// be acquired.
@@ -80,22 +94,69 @@ impl<T> PushLock<T> {
     /// While the underlying function does allow for recursion, this atomically increments a shared
     /// reader counter. Since dropping the RAII guard releases the lock by atomically decrementing
     /// this shared counter, it will eventually reach zero once all RAII guards have been dropped. 
+    ///
+    /// The token shows that the caller runs at `IRQL <= APC_LEVEL`.
     #[inline]
-    pub fn read(&mut self) -> Option<PushLockReadGuard<T>> {
+    pub fn read(&self, _irql: &impl AtMostApc) -> PushLockReadGuard<T> {
+        self.acquire_read()
+    }
+
+    /// Locks the lock for shared access without a token, for code that knows it runs at
+    /// `IRQL <= APC_LEVEL`.
+    #[inline]
+    pub(crate) fn acquire_read(&self) -> PushLockReadGuard<T> {
+        crate::debug_assert_irql!(<= APC_LEVEL);
+
         unsafe {
             KeEnterCriticalRegion()
         };
 
         unsafe {
             ExAcquirePushLockShared(
-                &mut *self.lock,
+                self.lock.get(),
             )
         };
 
-        Some(PushLockReadGuard {
-            lock: &mut self.lock,
-            data: unsafe { &mut *self.data.get() },
-        })
+        PushLockReadGuard {
+            lock: self,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Attempts to lock this [`PushLock`] with shared read access.
+    ///
+    /// If the lock could not be acquired at this time, then `None` is returned. Otherwise, an RAII
+    /// guard is returned which will release the shared access when dropped.
+    ///
+    /// This function does not block. The token shows that the caller runs at
+    /// `IRQL <= APC_LEVEL`.
+    #[inline]
+    pub fn try_read(&self, _irql: &impl AtMostApc) -> Option<PushLockReadGuard<T>> {
+        crate::debug_assert_irql!(<= APC_LEVEL);
+
+        unsafe {
+            KeEnterCriticalRegion()
+        };
+
+        let status = unsafe {
+            ExTryAcquirePushLockShared(
+                self.lock.get(),
+            )
+        } != 0;
+
+        match status {
+            true => Some(PushLockReadGuard {
+                lock: self,
+                _marker: PhantomData,
+            }),
+            _ => {
+                unsafe {
+                    KeLeaveCriticalRegion()
+                };
+
+                None
+            }
+        }
     }
 
     /// Locks this [`PushLock`] with exclusive write access, blocking the current thread until it can
@@ -110,22 +171,69 @@ impl<T> PushLock<T> {
     /// shared access but that do not currently hold the lock for shared access.
     ///
     /// The underlying function does not allow for recursion, which ensures correct behavior. 
+    ///
+    /// The token shows that the caller runs at `IRQL <= APC_LEVEL`.
+    #[inline]
+    pub fn write(&self, _irql: &impl AtMostApc) -> PushLockWriteGuard<T> {
+        self.acquire_write()
+    }
+
+    /// Locks the lock for exclusive access without a token, for code that knows it runs at
+    /// `IRQL <= APC_LEVEL`.
     #[inline]
-    pub fn write(&mut self) -> Option<PushLockWriteGuard<T>> {
+    pub(crate) fn acquire_write(&self) -> PushLockWriteGuard<T> {
+        crate::debug_assert_irql!(<= APC_LEVEL);
+
         unsafe {
             KeEnterCriticalRegion()
         };
 
         unsafe {
             ExAcquirePushLockExclusive(
-                &mut *self.lock,
+                self.lock.get(),
             )
         };
 
-        Some(PushLockWriteGuard {
-            lock: &mut self.lock,
-            data: unsafe { &mut *self.data.get() },
-        })
+        PushLockWriteGuard {
+            lock: self,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Attempts to lock this [`PushLock`] with exclusive write access.
+    ///
+    /// If the lock could not be acquired at this time, then `None` is returned. Otherwise, an RAII
+    /// guard is returned which will release the exclusive access when dropped.
+    ///
+    /// This function does not block. The token shows that the caller runs at
+    /// `IRQL <= APC_LEVEL`.
+    #[inline]
+    pub fn try_write(&self, _irql: &impl AtMostApc) -> Option<PushLockWriteGuard<T>> {
+        crate::debug_assert_irql!(<= APC_LEVEL);
+
+        unsafe {
+            KeEnterCriticalRegion()
+        };
+
+        let status = unsafe {
+            ExTryAcquirePushLockExclusive(
+                self.lock.get(),
+            )
+        } != 0;
+
+        match status {
+            true => Some(PushLockWriteGuard {
+                lock: self,
+                _marker: PhantomData,
+            }),
+            _ => {
+                unsafe {
+                    KeLeaveCriticalRegion()
+                };
+
+                None
+            }
+        }
     }
 }
 
@@ -136,15 +244,15 @@ impl<T> PushLock<T> {
 /// [`read`]: PushLock::read
 /// [`try_read`]: PushLock::try_read
 pub struct PushLockReadGuard<'a, T: 'a + ?Sized> {
-    pub(crate) lock: &'a mut EX_PUSH_LOCK,
-    pub(crate) data: &'a T,
+    pub(crate) lock: &'a PushLock<T>,
+    pub(crate) _marker: PhantomData<*const ()>,
 }
 
 impl<'a, T: ?Sized> Drop for PushLockReadGuard<'a, T> {
     fn drop(&mut self) {
         unsafe {
             ExReleasePushLockShared(
-                &mut *self.lock,
+                self.lock.lock.get(),
             )
         };
 
@@ -158,7 +266,7 @@ impl<'a, T: ?Sized> Deref for PushLockReadGuard<'a, T> {
     type Target = T;
 
     fn deref(&self) -> &T {
-        self.data
+        unsafe { &*self.lock.data.get() }
     }
 }
 
@@ -169,15 +277,15 @@ impl<'a, T: ?Sized> Deref for PushLockReadGuard<'a, T> {
 /// [`write`]: PushLock::write
 /// [`try_write`]: PushLock::try_write
 pub struct PushLockWriteGuard<'a, T: 'a + ?Sized> {
-    pub(crate) lock: &'a mut EX_PUSH_LOCK,
-    pub(crate) data: &'a mut T,
+    pub(crate) lock: &'a PushLock<T>,
+    pub(crate) _marker: PhantomData<*const ()>,
 }
 
 impl<'a, T: ?Sized> Drop for PushLockWriteGuard<'a, T> {
     fn drop(&mut self) {
         unsafe {
             ExReleasePushLockExclusive(
-                &mut *self.lock,
+                self.lock.lock.get(),
             )
         };
 
@@ -191,12 +299,12 @@ impl<'a, T: ?Sized> Deref for PushLockWriteGuard<'a, T> {
     type Target = T;
 
     fn deref(&self) -> &T {
-        self.data
+        unsafe { &*self.lock.data.get() }
     }
 }
 
 impl<'a, T: ?Sized> DerefMut for PushLockWriteGuard<'a, T> {
     fn deref_mut(&mut self) -> &mut T {
-        self.data
+        unsafe { &mut *self.lock.data.get() }
     }
 }
@@ -0,0 +1,364 @@
+use alloc::boxed::Box;
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+use crate::error::{Error, IntoResult};
+use crate::irql::AtMostApc;
+use windows_kernel_sys::base::ERESOURCE;
+use windows_kernel_sys::ntoskrnl::{
+    ExAcquireResourceExclusiveLite,
+    ExAcquireResourceSharedLite,
+    ExAcquireSharedStarveExclusive,
+    ExConvertExclusiveToSharedLite,
+    ExDeleteResourceLite,
+    ExInitializeResourceLite,
+    ExIsResourceAcquiredExclusiveLite,
+    ExIsResourceAcquiredSharedLite,
+    ExReleaseResourceLite,
+    KeEnterCriticalRegion,
+    KeLeaveCriticalRegion,
+};
+
+/// A [`Resource`] is a reader-writer lock built on top of an `ERESOURCE`, as typically used by
+/// file systems and drivers that sit next to them.
+///
+/// Unlike a [`PushLock`], a thread that holds the resource for shared access can acquire it for
+/// shared access again, and a thread that holds the resource for exclusive access can acquire it
+/// again for shared access through [`ResourceWriteGuard::read_recursive`]. The resource also
+/// supports non-blocking acquisition, acquisition for shared access that starves threads waiting
+/// for exclusive access through [`read_starve_exclusive`], and converting exclusive access into
+/// shared access through [`ResourceWriteGuard::downgrade`].
+///
+/// Acquiring the resource enters a critical region, which is left again when the guard gets
+/// dropped. The resource can only be acquired at `IRQL <= APC_LEVEL`, which the methods that
+/// acquire the resource require a token for.
+///
+/// [`PushLock`]: crate::sync::push_lock::PushLock
+/// [`read_starve_exclusive`]: Resource::read_starve_exclusive
+pub struct Resource<T: ?Sized> {
+    // The `ERESOURCE` is linked into the global list of resources, hence it must not move.
+    pub(crate) lock: Box<UnsafeCell<ERESOURCE>>,
+    pub(crate) data: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for Resource<T> {}
+unsafe impl<T: ?Sized + Send + Sync> Sync for Resource<T> {}
+
+impl<T> Resource<T> {
+    /// Creates a new resource that is not acquired.
+    pub fn new(data: T) -> Result<Self, Error> {
+        let lock: Box<UnsafeCell<ERESOURCE>> = Box::new(UnsafeCell::new(unsafe {
+            core::mem::zeroed()
+        }));
+
+        unsafe {
+            ExInitializeResourceLite(
+                lock.get(),
+            )
+        }.into_result()?;
+
+        Ok(Self {
+            lock,
+            data: UnsafeCell::new(data),
+        })
+    }
+}
+
+impl<T: ?Sized> Resource<T> {
+    /// Returns a mutable reference to the underlying data. As this borrows the [`Resource`]
+    /// mutably, no locking is needed.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    /// Returns whether the current thread holds the resource for exclusive access.
+    pub fn is_acquired_exclusive(&self) -> bool {
+        unsafe {
+            ExIsResourceAcquiredExclusiveLite(
+                self.lock.get(),
+            ) != 0
+        }
+    }
+
+    /// Returns the number of times that the current thread has acquired the resource, either for
+    /// shared or for exclusive access.
+    pub fn acquired_count(&self) -> u32 {
+        unsafe {
+            ExIsResourceAcquiredSharedLite(
+                self.lock.get(),
+            )
+        }
+    }
+
+    fn acquire_shared<F>(&self, acquire: F) -> Option<ResourceReadGuard<T>>
+    where
+        F: FnOnce(*mut ERESOURCE) -> u8,
+    {
+        crate::debug_assert_irql!(<= APC_LEVEL);
+
+        // Acquiring shared access while holding exclusive access would hand out a shared
+        // reference next to a mutable one.
+        assert!(
+            !self.is_acquired_exclusive(),
+            "the resource is held exclusively, use `ResourceWriteGuard::read_recursive` instead",
+        );
+
+        unsafe {
+            KeEnterCriticalRegion()
+        };
+
+        match acquire(self.lock.get()) != 0 {
+            true => Some(ResourceReadGuard {
+                lock: self,
+                _marker: PhantomData,
+            }),
+            _ => {
+                unsafe {
+                    KeLeaveCriticalRegion()
+                };
+
+                None
+            }
+        }
+    }
+
+    fn acquire_exclusive(&self, wait: bool) -> Option<ResourceWriteGuard<T>> {
+        crate::debug_assert_irql!(<= APC_LEVEL);
+
+        // Acquiring exclusive access recursively would hand out a second mutable reference, while
+        // acquiring exclusive access while holding shared access deadlocks.
+        assert!(
+            self.acquired_count() == 0,
+            "the resource is already held by the current thread",
+        );
+
+        unsafe {
+            KeEnterCriticalRegion()
+        };
+
+        let acquired = unsafe {
+            ExAcquireResourceExclusiveLite(
+                self.lock.get(),
+                wait as _,
+            )
+        } != 0;
+
+        match acquired {
+            true => Some(ResourceWriteGuard {
+                lock: self,
+                _marker: PhantomData,
+            }),
+            _ => {
+                unsafe {
+                    KeLeaveCriticalRegion()
+                };
+
+                None
+            }
+        }
+    }
+
+    /// Acquires the resource for shared access, blocking the current thread until it can be
+    /// acquired. The current thread may already hold the resource for shared access.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the current thread holds the resource for exclusive access, in which case
+    /// [`ResourceWriteGuard::read_recursive`] should be used instead.
+    #[inline]
+    pub fn read(&self, _irql: &impl AtMostApc) -> ResourceReadGuard<T> {
+        self.acquire_shared(|lock| unsafe { ExAcquireResourceSharedLite(lock, true as _) })
+            .unwrap()
+    }
+
+    /// Attempts to acquire the resource for shared access without blocking. Returns `None` if the
+    /// resource could not be acquired at this time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the current thread holds the resource for exclusive access.
+    #[inline]
+    pub fn try_read(&self, _irql: &impl AtMostApc) -> Option<ResourceReadGuard<T>> {
+        self.acquire_shared(|lock| unsafe { ExAcquireResourceSharedLite(lock, false as _) })
+    }
+
+    /// Acquires the resource for shared access, even if there are threads waiting to acquire the
+    /// resource for exclusive access, thereby starving those threads. Blocks the current thread
+    /// only while another thread holds the resource for exclusive access.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the current thread holds the resource for exclusive access.
+    #[inline]
+    pub fn read_starve_exclusive(&self, _irql: &impl AtMostApc) -> ResourceReadGuard<T> {
+        self.acquire_shared(|lock| unsafe { ExAcquireSharedStarveExclusive(lock, true as _) })
+            .unwrap()
+    }
+
+    /// Attempts to acquire the resource for shared access without blocking, even if there are
+    /// threads waiting to acquire the resource for exclusive access.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the current thread holds the resource for exclusive access.
+    #[inline]
+    pub fn try_read_starve_exclusive(&self, _irql: &impl AtMostApc) -> Option<ResourceReadGuard<T>> {
+        self.acquire_shared(|lock| unsafe { ExAcquireSharedStarveExclusive(lock, false as _) })
+    }
+
+    /// Acquires the resource for exclusive access, blocking the current thread until it can be
+    /// acquired.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the current thread already holds the resource, as exclusive access cannot be
+    /// acquired recursively and shared access cannot be upgraded to exclusive access.
+    #[inline]
+    pub fn write(&self, _irql: &impl AtMostApc) -> ResourceWriteGuard<T> {
+        self.acquire_exclusive(true).unwrap()
+    }
+
+    /// Attempts to acquire the resource for exclusive access without blocking. Returns `None` if
+    /// the resource could not be acquired at this time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the current thread already holds the resource.
+    #[inline]
+    pub fn try_write(&self, _irql: &impl AtMostApc) -> Option<ResourceWriteGuard<T>> {
+        self.acquire_exclusive(false)
+    }
+}
+
+impl<T: ?Sized> Drop for Resource<T> {
+    fn drop(&mut self) {
+        unsafe {
+            ExDeleteResourceLite(
+                self.lock.get(),
+            )
+        };
+    }
+}
+
+/// RAII structure used to release the shared access of a resource when dropped, which also leaves
+/// the critical region.
+///
+/// This structure is created by the [`read`], [`try_read`], [`read_starve_exclusive`] and
+/// [`try_read_starve_exclusive`] methods on [`Resource`], and by
+/// [`ResourceWriteGuard::read_recursive`] and [`ResourceWriteGuard::downgrade`].
+///
+/// [`read`]: Resource::read
+/// [`try_read`]: Resource::try_read
+/// [`read_starve_exclusive`]: Resource::read_starve_exclusive
+/// [`try_read_starve_exclusive`]: Resource::try_read_starve_exclusive
+pub struct ResourceReadGuard<'a, T: 'a + ?Sized> {
+    pub(crate) lock: &'a Resource<T>,
+    pub(crate) _marker: PhantomData<*const ()>,
+}
+
+impl<'a, T: ?Sized> Drop for ResourceReadGuard<'a, T> {
+    fn drop(&mut self) {
+        unsafe {
+            ExReleaseResourceLite(
+                self.lock.lock.get(),
+            )
+        };
+
+        unsafe {
+            KeLeaveCriticalRegion()
+        };
+    }
+}
+
+impl<'a, T: ?Sized> Deref for ResourceReadGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+/// RAII structure used to release the exclusive access of a resource when dropped, which also
+/// leaves the critical region.
+///
+/// This structure is created by the [`write`] and [`try_write`] methods on [`Resource`].
+///
+/// [`write`]: Resource::write
+/// [`try_write`]: Resource::try_write
+pub struct ResourceWriteGuard<'a, T: 'a + ?Sized> {
+    pub(crate) lock: &'a Resource<T>,
+    pub(crate) _marker: PhantomData<*const ()>,
+}
+
+impl<'a, T: ?Sized> ResourceWriteGuard<'a, T> {
+    /// Acquires the resource recursively for shared access, e.g. to pass it to code that expects
+    /// a [`ResourceReadGuard`]. The exclusive access cannot be used while the returned guard
+    /// exists.
+    pub fn read_recursive(&self) -> ResourceReadGuard<T> {
+        unsafe {
+            KeEnterCriticalRegion()
+        };
+
+        // This never blocks, as the current thread holds the resource for exclusive access.
+        unsafe {
+            ExAcquireResourceSharedLite(
+                self.lock.lock.get(),
+                true as _,
+            )
+        };
+
+        ResourceReadGuard {
+            lock: self.lock,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Converts the exclusive access into shared access, without releasing the resource in
+    /// between. This allows other threads that are waiting for shared access to continue.
+    pub fn downgrade(self) -> ResourceReadGuard<'a, T> {
+        let lock = self.lock;
+
+        unsafe {
+            ExConvertExclusiveToSharedLite(
+                lock.lock.get(),
+            )
+        };
+
+        // The shared access is released and the critical region is left by the read guard.
+        core::mem::forget(self);
+
+        ResourceReadGuard {
+            lock,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, T: ?Sized> Drop for ResourceWriteGuard<'a, T> {
+    fn drop(&mut self) {
+        unsafe {
+            ExReleaseResourceLite(
+                self.lock.lock.get(),
+            )
+        };
+
+        unsafe {
+            KeLeaveCriticalRegion()
+        };
+    }
+}
+
+impl<'a, T: ?Sized> Deref for ResourceWriteGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<'a, T: ?Sized> DerefMut for ResourceWriteGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
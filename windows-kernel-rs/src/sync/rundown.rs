@@ -0,0 +1,110 @@
+use core::cell::UnsafeCell;
+use windows_kernel_sys::base::EX_RUNDOWN_REF;
+use windows_kernel_sys::ntoskrnl::{
+    ExAcquireRundownProtection,
+    ExReleaseRundownProtection,
+    ExReInitializeRundownProtection,
+    ExRundownCompleted,
+    ExWaitForRundownProtectionRelease,
+};
+
+/// Rundown protection for the driver itself. Work items and callbacks that run code of the driver
+/// should hold a guard of this, such that the [`kernel_module!`] macro can wait for them to finish
+/// before the driver gets unloaded. Threads spawned through [`thread::spawn`] keep the driver
+/// loaded through the driver object instead.
+///
+/// [`kernel_module!`]: crate::kernel_module
+/// [`thread::spawn`]: crate::thread::spawn
+pub static DRIVER_RUNDOWN: Rundown = Rundown::new();
+
+/// A [`Rundown`] protects an object that is about to be torn down. Any number of threads can
+/// acquire run-time protection of the object through [`acquire`], until [`wait`] is called. From
+/// then on any attempt to acquire protection fails, while [`wait`] blocks until all of the
+/// outstanding [`RundownGuard`]s have been dropped.
+///
+/// Unlike a lock, acquiring and releasing run-time protection never blocks and can be done at
+/// `IRQL <= DISPATCH_LEVEL`. [`wait`] can only be called at `PASSIVE_LEVEL`.
+///
+/// [`acquire`]: Rundown::acquire
+/// [`wait`]: Rundown::wait
+pub struct Rundown {
+    // The `EX_RUNDOWN_REF` only holds a reference count while there are no waiters, hence it is
+    // safe to move it around as long as nobody borrows it.
+    raw: UnsafeCell<EX_RUNDOWN_REF>,
+}
+
+unsafe impl Send for Rundown {}
+unsafe impl Sync for Rundown {}
+
+impl Rundown {
+    /// Creates a new [`Rundown`] that allows run-time protection to be acquired.
+    pub const fn new() -> Self {
+        // This is what `ExInitializeRundownProtection` does, which is not a `const fn`.
+        Self {
+            raw: UnsafeCell::new(unsafe { core::mem::zeroed() }),
+        }
+    }
+
+    /// Attempts to acquire run-time protection. Returns `None` if the object is being run down.
+    #[inline]
+    pub fn acquire(&self) -> Option<RundownGuard> {
+        let acquired = unsafe {
+            ExAcquireRundownProtection(self.raw.get())
+        } != 0;
+
+        match acquired {
+            true => Some(RundownGuard {
+                rundown: self,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Prevents any further run-time protection from being acquired, and blocks the current
+    /// thread until all of the outstanding [`RundownGuard`]s have been dropped. Once this
+    /// returns, the object can be torn down safely.
+    ///
+    /// Calling this more than once is fine, as long as [`reinitialize`] has not been called in
+    /// between.
+    ///
+    /// [`reinitialize`]: Rundown::reinitialize
+    pub fn wait(&self) {
+        unsafe {
+            ExWaitForRundownProtectionRelease(self.raw.get());
+            ExRundownCompleted(self.raw.get());
+        }
+    }
+
+    /// Allows run-time protection to be acquired again after [`wait`] has returned.
+    ///
+    /// [`wait`]: Rundown::wait
+    pub fn reinitialize(&self) {
+        unsafe {
+            ExReInitializeRundownProtection(self.raw.get());
+        }
+    }
+}
+
+impl Default for Rundown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An RAII implementation of run-time protection. When this structure is dropped (falls out of
+/// scope), the run-time protection is released.
+///
+/// This structure is created by the [`acquire`] method on [`Rundown`].
+///
+/// [`acquire`]: Rundown::acquire
+pub struct RundownGuard<'a> {
+    rundown: &'a Rundown,
+}
+
+impl<'a> Drop for RundownGuard<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            ExReleaseRundownProtection(self.rundown.raw.get());
+        }
+    }
+}
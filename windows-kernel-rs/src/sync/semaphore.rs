@@ -0,0 +1,82 @@
+use alloc::boxed::Box;
+use core::cell::UnsafeCell;
+use crate::error::{Error, IntoResult};
+use crate::sync::wait::Waitable;
+use windows_kernel_sys::base::{KSEMAPHORE, PVOID};
+use windows_kernel_sys::ntoskrnl::{KeInitializeSemaphore, KeReadStateSemaphore, KeReleaseSemaphoreSafe};
+
+/// A [`Semaphore`] is a dispatcher object that maintains a count. The semaphore is signaled while
+/// the count is greater than zero, and every wait that is satisfied decrements the count. Waiting
+/// is done through the [`Waitable`] trait, while [`release`] increments the count.
+///
+/// [`release`]: Semaphore::release
+pub struct Semaphore {
+    // The `KSEMAPHORE` contains a wait list that points to itself, hence it must not move.
+    raw: Box<UnsafeCell<KSEMAPHORE>>,
+}
+
+unsafe impl Send for Semaphore {}
+unsafe impl Sync for Semaphore {}
+
+impl Semaphore {
+    /// Creates a new semaphore with the given initial count, that can be incremented up to the
+    /// given limit.
+    pub fn new(count: i32, limit: i32) -> Result<Self, Error> {
+        if count < 0 || limit <= 0 || count > limit {
+            return Err(Error::INVALID_PARAMETER);
+        }
+
+        let raw: Box<UnsafeCell<KSEMAPHORE>> = Box::new(UnsafeCell::new(unsafe {
+            core::mem::zeroed()
+        }));
+
+        unsafe {
+            KeInitializeSemaphore(
+                raw.get(),
+                count,
+                limit,
+            )
+        };
+
+        Ok(Self {
+            raw,
+        })
+    }
+
+    /// Increments the count of the semaphore by the given amount, waking up waiting threads, and
+    /// returns the previous count. Returns [`Error::SEMAPHORE_LIMIT_EXCEEDED`] if the count would
+    /// exceed the limit. This can be called at `IRQL <= DISPATCH_LEVEL`.
+    pub fn release(&self, adjustment: i32) -> Result<i32, Error> {
+        if adjustment <= 0 {
+            return Err(Error::INVALID_PARAMETER);
+        }
+
+        let mut previous = 0;
+
+        unsafe {
+            KeReleaseSemaphoreSafe(
+                self.raw.get(),
+                0,
+                adjustment,
+                &mut previous,
+            )
+        }.into_result()?;
+
+        Ok(previous)
+    }
+
+    /// Returns the current count of the semaphore.
+    pub fn count(&self) -> i32 {
+        unsafe {
+            KeReadStateSemaphore(
+                self.raw.get(),
+            )
+        }
+    }
+}
+
+unsafe impl Waitable for Semaphore {
+    fn as_dispatcher_object(&self) -> PVOID {
+        self.raw.get() as _
+    }
+}
@@ -0,0 +1,151 @@
+use alloc::boxed::Box;
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use windows_kernel_sys::base::{PSLIST_ENTRY, SLIST_ENTRY, SLIST_HEADER};
+use windows_kernel_sys::ntoskrnl::{
+    ExInterlockedPushEntrySList,
+    InterlockedFlushSList,
+    InterlockedPopEntrySList,
+};
+
+// The entry has to be the first field, such that a pointer to the entry is a pointer to the node.
+// `SLIST_ENTRY` is 16-byte aligned, which the pool allocations backing the boxes satisfy.
+#[repr(C)]
+struct Node<T> {
+    entry: SLIST_ENTRY,
+    value: T,
+}
+
+/// A lock-free, interlocked singly linked list built on top of an `SLIST_HEADER`, which is
+/// typically used to pass work from DPCs to worker threads, or to keep a cache of free objects.
+///
+/// Values are pushed to and popped from the front of the list, i.e. the list behaves as a stack.
+/// Every value is stored in its own boxed node, which is allocated from the non-paged pool when
+/// the value gets pushed and freed when the value gets popped.
+///
+/// All of the operations can be used at `IRQL <= DISPATCH_LEVEL`, and none of them block.
+pub struct SList<T> {
+    // The header only holds a pointer to the first entry, hence it is safe to move it around as
+    // long as nobody borrows it.
+    head: UnsafeCell<SLIST_HEADER>,
+    _marker: PhantomData<Box<Node<T>>>,
+}
+
+unsafe impl<T: Send> Send for SList<T> {}
+unsafe impl<T: Send> Sync for SList<T> {}
+
+impl<T> SList<T> {
+    /// Creates a new empty list.
+    pub const fn new() -> Self {
+        // This is what `InitializeSListHead` does, which is not a `const fn`.
+        Self {
+            head: UnsafeCell::new(unsafe { core::mem::zeroed() }),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Pushes the value to the front of the list. Returns `true` if the list was empty, e.g. to
+    /// only queue a work item for the first value.
+    pub fn push(&self, value: T) -> bool {
+        let node = Box::into_raw(Box::new(Node {
+            entry: unsafe { core::mem::zeroed() },
+            value,
+        }));
+
+        let previous = unsafe {
+            ExInterlockedPushEntrySList(
+                self.head.get(),
+                node as PSLIST_ENTRY,
+            )
+        };
+
+        previous.is_null()
+    }
+
+    /// Pops the value from the front of the list. Returns `None` if the list is empty.
+    pub fn pop(&self) -> Option<T> {
+        let entry = unsafe {
+            InterlockedPopEntrySList(
+                self.head.get(),
+            )
+        };
+
+        match entry.is_null() {
+            true => None,
+            _ => Some(unsafe { Box::from_raw(entry as *mut Node<T>) }.value),
+        }
+    }
+
+    /// Removes all of the values from the list at once. Returns an iterator over the values, from
+    /// the most recently pushed value to the least recently pushed value. The values that have
+    /// not been iterated over are dropped together with the iterator.
+    pub fn pop_all(&self) -> IntoIter<T> {
+        let entry = unsafe {
+            InterlockedFlushSList(
+                self.head.get(),
+            )
+        };
+
+        IntoIter {
+            entry,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Default for SList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for SList<T> {
+    fn drop(&mut self) {
+        drop(self.pop_all());
+    }
+}
+
+impl<T> IntoIterator for SList<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> IntoIter<T> {
+        self.pop_all()
+    }
+}
+
+/// An iterator over the values that have been removed from an [`SList`].
+///
+/// This structure is created by the [`pop_all`] method on [`SList`], and by its [`IntoIterator`]
+/// implementation.
+///
+/// [`pop_all`]: SList::pop_all
+pub struct IntoIter<T> {
+    entry: PSLIST_ENTRY,
+    _marker: PhantomData<Box<Node<T>>>,
+}
+
+unsafe impl<T: Send> Send for IntoIter<T> {}
+unsafe impl<T: Sync> Sync for IntoIter<T> {}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.entry.is_null() {
+            return None;
+        }
+
+        // The entries are no longer part of the list, hence nobody else has access to them.
+        let node = unsafe { Box::from_raw(self.entry as *mut Node<T>) };
+        self.entry = node.entry.Next;
+
+        Some(node.value)
+    }
+}
+
+impl<T> Drop for IntoIter<T> {
+    fn drop(&mut self) {
+        for _ in self {}
+    }
+}
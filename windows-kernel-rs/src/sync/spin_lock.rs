@@ -0,0 +1,219 @@
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+use windows_kernel_sys::base::{KIRQL, KSPIN_LOCK};
+use windows_kernel_sys::ntoskrnl::{
+    KeAcquireSpinLock,
+    KeReleaseSpinLock,
+    KeAcquireSpinLockAtDpcLevel,
+    KeReleaseSpinLockFromDpcLevel,
+    KeTryToAcquireSpinLockAtDpcLevel,
+};
+
+/// A [`SpinLock`] is a mutual exclusion primitive that busy-waits for the lock to become
+/// available, rather than blocking the thread. Unlike a [`FastMutex`], it can be acquired at
+/// `IRQL <= DISPATCH_LEVEL`, e.g. from DPCs and completion routines, and the lock has to be stored
+/// in non-paged memory.
+///
+/// Acquiring the lock through [`lock`] raises the IRQL to `DISPATCH_LEVEL`, and the guard restores
+/// the previous IRQL when dropped. Code that is already running at `DISPATCH_LEVEL` can use
+/// [`lock_at_dpc_level`] instead, which avoids changing the IRQL.
+///
+/// As the IRQL is raised to `DISPATCH_LEVEL` while holding the lock, the code holding the lock
+/// must not access paged memory or wait for dispatcher objects, and should hold the lock as short
+/// as possible.
+///
+/// [`FastMutex`]: crate::sync::fast_mutex::FastMutex
+/// [`lock`]: SpinLock::lock
+/// [`lock_at_dpc_level`]: SpinLock::lock_at_dpc_level
+pub struct SpinLock<T: ?Sized> {
+    pub(crate) lock: UnsafeCell<KSPIN_LOCK>,
+    pub(crate) data: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for SpinLock<T> {}
+unsafe impl<T: ?Sized + Send> Sync for SpinLock<T> {}
+
+impl<T> SpinLock<T> {
+    /// Creates a new spin lock in an unlocked state ready for use.
+    pub const fn new(data: T) -> Self {
+        // This is what `KeInitializeSpinLock` does, which is not a `const fn`.
+        Self {
+            lock: UnsafeCell::new(0),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    /// Consumes this [`SpinLock`], returning the underlying data.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        let Self { data, .. } = self;
+        data.into_inner()
+    }
+}
+
+impl<T: ?Sized> SpinLock<T> {
+    /// Returns a mutable reference to the underlying data. As this borrows the [`SpinLock`]
+    /// mutably, no locking is needed.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    /// Acquires the spin lock, raising the IRQL to `DISPATCH_LEVEL` and spinning until the lock
+    /// becomes available. The caller must be running at `IRQL <= DISPATCH_LEVEL`.
+    ///
+    /// Returns an RAII guard which releases the lock and restores the previous IRQL when dropped.
+    /// The lock cannot be acquired recursively.
+    #[inline]
+    pub fn lock(&self) -> SpinLockGuard<T> {
+        crate::debug_assert_irql!(<= DISPATCH_LEVEL);
+
+        let mut old_irql: KIRQL = 0;
+
+        unsafe {
+            KeAcquireSpinLock(
+                self.lock.get(),
+                &mut old_irql,
+            )
+        };
+
+        SpinLockGuard {
+            lock: self,
+            old_irql,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Acquires the spin lock without changing the IRQL. The caller must already be running at
+    /// `DISPATCH_LEVEL`, e.g. in a DPC.
+    ///
+    /// Returns an RAII guard which releases the lock when dropped.
+    #[inline]
+    pub fn lock_at_dpc_level(&self) -> SpinLockDpcGuard<T> {
+        crate::debug_assert_irql!(== DISPATCH_LEVEL);
+
+        unsafe {
+            KeAcquireSpinLockAtDpcLevel(
+                self.lock.get(),
+            )
+        };
+
+        SpinLockDpcGuard {
+            lock: self,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Attempts to acquire the spin lock without changing the IRQL. The caller must already be
+    /// running at `DISPATCH_LEVEL`.
+    ///
+    /// If the lock is currently held, then `None` is returned. Otherwise an RAII guard is returned
+    /// which releases the lock when dropped.
+    #[inline]
+    pub fn try_lock_at_dpc_level(&self) -> Option<SpinLockDpcGuard<T>> {
+        crate::debug_assert_irql!(== DISPATCH_LEVEL);
+
+        let status = unsafe {
+            KeTryToAcquireSpinLockAtDpcLevel(
+                self.lock.get(),
+            )
+        } != 0;
+
+        match status {
+            true => Some(SpinLockDpcGuard {
+                lock: self,
+                _marker: PhantomData,
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl<T: ?Sized + Default> Default for SpinLock<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T> From<T> for SpinLock<T> {
+    fn from(data: T) -> Self {
+        Self::new(data)
+    }
+}
+
+/// An RAII implementation of a "scoped lock" of a spin lock. When this structure is dropped (falls
+/// out of scope), the lock will be released and the previous IRQL will be restored.
+///
+/// This structure is created by the [`lock`] method on [`SpinLock`]. The guard has to be dropped
+/// on the same processor, hence it cannot be sent to another thread.
+///
+/// [`lock`]: SpinLock::lock
+pub struct SpinLockGuard<'a, T: 'a + ?Sized> {
+    lock: &'a SpinLock<T>,
+    old_irql: KIRQL,
+    _marker: PhantomData<*const ()>,
+}
+
+impl<'a, T: ?Sized> Drop for SpinLockGuard<'a, T> {
+    fn drop(&mut self) {
+        unsafe {
+            KeReleaseSpinLock(
+                self.lock.lock.get(),
+                self.old_irql,
+            )
+        };
+    }
+}
+
+impl<'a, T: ?Sized> Deref for SpinLockGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<'a, T: ?Sized> DerefMut for SpinLockGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+/// An RAII implementation of a "scoped lock" of a spin lock that was acquired at `DISPATCH_LEVEL`.
+/// When this structure is dropped (falls out of scope), the lock will be released without changing
+/// the IRQL.
+///
+/// This structure is created by the [`lock_at_dpc_level`] and [`try_lock_at_dpc_level`] methods
+/// on [`SpinLock`].
+///
+/// [`lock_at_dpc_level`]: SpinLock::lock_at_dpc_level
+/// [`try_lock_at_dpc_level`]: SpinLock::try_lock_at_dpc_level
+pub struct SpinLockDpcGuard<'a, T: 'a + ?Sized> {
+    lock: &'a SpinLock<T>,
+    _marker: PhantomData<*const ()>,
+}
+
+impl<'a, T: ?Sized> Drop for SpinLockDpcGuard<'a, T> {
+    fn drop(&mut self) {
+        unsafe {
+            KeReleaseSpinLockFromDpcLevel(
+                self.lock.lock.get(),
+            )
+        };
+    }
+}
+
+impl<'a, T: ?Sized> Deref for SpinLockDpcGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<'a, T: ?Sized> DerefMut for SpinLockDpcGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
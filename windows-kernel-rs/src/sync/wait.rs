@@ -0,0 +1,163 @@
+use crate::error::Error;
+use crate::time::{SystemTime, Timeout};
+use alloc::vec::Vec;
+use core::time::Duration;
+use windows_kernel_sys::base::{KWAIT_BLOCK, LARGE_INTEGER, NTSTATUS, PVOID};
+use windows_kernel_sys::base::{MAXIMUM_WAIT_OBJECTS, THREAD_WAIT_OBJECTS};
+use windows_kernel_sys::base::{STATUS_ABANDONED_WAIT_0, STATUS_TIMEOUT, STATUS_WAIT_0};
+use windows_kernel_sys::base::_KWAIT_REASON as KWAIT_REASON;
+use windows_kernel_sys::base::_MODE as MODE;
+use windows_kernel_sys::base::_WAIT_TYPE as WAIT_TYPE;
+use windows_kernel_sys::ntoskrnl::{KeWaitForMultipleObjects, KeWaitForSingleObject};
+
+/// Waits for the dispatcher object to be signaled. Returns the status of `KeWaitForSingleObject`.
+pub(crate) unsafe fn wait_for_object(object: PVOID, timeout: Option<Timeout>) -> NTSTATUS {
+    let mut timeout = timeout.map(|timeout| timeout.as_large_integer());
+
+    let timeout_ptr = timeout.as_mut()
+        .map(|timeout| timeout as *mut LARGE_INTEGER)
+        .unwrap_or(core::ptr::null_mut());
+
+    KeWaitForSingleObject(
+        object,
+        KWAIT_REASON::Executive,
+        MODE::KernelMode as _,
+        false as _,
+        timeout_ptr,
+    )
+}
+
+/// Converts the status of a wait function into a result.
+pub(crate) fn wait_result(status: NTSTATUS) -> Result<(), Error> {
+    match status {
+        STATUS_WAIT_0 | STATUS_ABANDONED_WAIT_0 => Ok(()),
+        STATUS_TIMEOUT => Err(Error::IO_TIMEOUT),
+        status => Err(Error::from_ntstatus(status)),
+    }
+}
+
+/// A kernel dispatcher object that threads can wait for until it gets signaled, such as an
+/// [`Event`] or a [`Semaphore`].
+///
+/// Waiting without a timeout, or with a non-zero timeout, is only allowed at
+/// `IRQL <= APC_LEVEL`. Waiting with a zero timeout is allowed at `IRQL <= DISPATCH_LEVEL`.
+///
+/// # Safety
+///
+/// [`as_dispatcher_object`] must return a pointer to an initialized dispatcher object that stays
+/// valid for as long as `self` is borrowed, and waiting for it must not change any state that
+/// Rust tracks, such as whether a guard grants mutable access to data.
+///
+/// [`Event`]: crate::sync::event::Event
+/// [`Semaphore`]: crate::sync::semaphore::Semaphore
+/// [`as_dispatcher_object`]: Waitable::as_dispatcher_object
+pub unsafe trait Waitable {
+    /// Returns a pointer to the dispatcher object, e.g. a `KEVENT`.
+    fn as_dispatcher_object(&self) -> PVOID;
+
+    /// Blocks the current thread until the object gets signaled.
+    fn wait(&self) {
+        let _ = unsafe { wait_for_object(self.as_dispatcher_object(), None) };
+    }
+
+    /// Blocks the current thread until the object gets signaled, or until the timeout expires, in
+    /// which case [`Error::IO_TIMEOUT`] is returned.
+    fn wait_timeout(&self, timeout: Duration) -> Result<(), Error> {
+        let timeout = Timeout::Relative(timeout);
+
+        wait_result(unsafe { wait_for_object(self.as_dispatcher_object(), Some(timeout)) })
+    }
+
+    /// Blocks the current thread until the object gets signaled, or until the system clock
+    /// reaches the deadline, in which case [`Error::IO_TIMEOUT`] is returned.
+    fn wait_until(&self, deadline: SystemTime) -> Result<(), Error> {
+        let timeout = Timeout::Absolute(deadline);
+
+        wait_result(unsafe { wait_for_object(self.as_dispatcher_object(), Some(timeout)) })
+    }
+}
+
+fn wait_multiple(
+    objects: &[&dyn Waitable],
+    wait_type: WAIT_TYPE::Type,
+    timeout: Option<Timeout>,
+) -> Result<NTSTATUS, Error> {
+    if objects.is_empty() || objects.len() > MAXIMUM_WAIT_OBJECTS as usize {
+        return Err(Error::INVALID_PARAMETER);
+    }
+
+    let mut raw_objects = Vec::new();
+    raw_objects.try_reserve_exact(objects.len())
+        .map_err(|_| Error::INSUFFICIENT_RESOURCES)?;
+    raw_objects.extend(objects.iter().map(|object| object.as_dispatcher_object()));
+
+    // The kernel thread has wait blocks for up to `THREAD_WAIT_OBJECTS` objects. For more objects
+    // the caller has to provide the wait blocks.
+    let mut wait_blocks: Vec<KWAIT_BLOCK> = Vec::new();
+
+    if objects.len() > THREAD_WAIT_OBJECTS as usize {
+        wait_blocks.try_reserve_exact(objects.len())
+            .map_err(|_| Error::INSUFFICIENT_RESOURCES)?;
+    }
+
+    let wait_blocks_ptr = match wait_blocks.capacity() {
+        0 => core::ptr::null_mut(),
+        _ => wait_blocks.as_mut_ptr(),
+    };
+
+    let mut timeout = timeout.map(|timeout| timeout.as_large_integer());
+
+    let timeout_ptr = timeout.as_mut()
+        .map(|timeout| timeout as *mut LARGE_INTEGER)
+        .unwrap_or(core::ptr::null_mut());
+
+    let status = unsafe {
+        KeWaitForMultipleObjects(
+            raw_objects.len() as _,
+            raw_objects.as_mut_ptr(),
+            wait_type,
+            KWAIT_REASON::Executive,
+            MODE::KernelMode as _,
+            false as _,
+            timeout_ptr,
+            wait_blocks_ptr,
+        )
+    };
+
+    Ok(status)
+}
+
+/// Blocks the current thread until any of the objects gets signaled, and returns the index of the
+/// object that was signaled. Returns [`Error::IO_TIMEOUT`] if the timeout expires first. A mutex that
+/// was abandoned by its owner counts as signaled.
+///
+/// At most 64 objects can be waited for at once.
+///
+/// ```ignore
+/// let index = wait_any(&[&stop_event, &work_event], Some(Duration::from_secs(1).into()))?;
+/// ```
+pub fn wait_any(objects: &[&dyn Waitable], timeout: Option<Timeout>) -> Result<usize, Error> {
+    let count = objects.len() as NTSTATUS;
+    let status = wait_multiple(objects, WAIT_TYPE::WaitAny, timeout)?;
+
+    match status {
+        STATUS_TIMEOUT => Err(Error::IO_TIMEOUT),
+        status if (STATUS_WAIT_0..STATUS_WAIT_0 + count).contains(&status) => {
+            Ok((status - STATUS_WAIT_0) as usize)
+        }
+        status if (STATUS_ABANDONED_WAIT_0..STATUS_ABANDONED_WAIT_0 + count).contains(&status) => {
+            Ok((status - STATUS_ABANDONED_WAIT_0) as usize)
+        }
+        status => Err(Error::from_ntstatus(status)),
+    }
+}
+
+/// Blocks the current thread until all of the objects are signaled at the same time. Returns
+/// [`Error::IO_TIMEOUT`] if the timeout expires first.
+///
+/// At most 64 objects can be waited for at once.
+pub fn wait_all(objects: &[&dyn Waitable], timeout: Option<Timeout>) -> Result<(), Error> {
+    let status = wait_multiple(objects, WAIT_TYPE::WaitAll, timeout)?;
+
+    wait_result(status)
+}
@@ -0,0 +1,374 @@
+//! This module provides functions to spawn system threads, to wait for them to finish, and to
+//! control the current thread.
+
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use core::cell::UnsafeCell;
+use core::time::Duration;
+use crate::affinity::get_processor_number;
+use crate::driver::driver_object;
+use crate::error::{Error, IntoResult};
+use crate::sync::wait::{wait_for_object, Waitable};
+use crate::time::Timeout;
+use windows_kernel_sys::base::{
+    GROUP_AFFINITY, HANDLE, KPRIORITY, OBJECT_ATTRIBUTES, OBJ_KERNEL_HANDLE, PETHREAD, PVOID,
+    STATUS_WAIT_0, THREAD_ALL_ACCESS,
+};
+use windows_kernel_sys::base::_MODE as MODE;
+use windows_kernel_sys::ntoskrnl::{
+    KeDelayExecutionThread, KeGetCurrentThread, KeQueryPriorityThread, KeSetPriorityThread,
+    KeSetSystemGroupAffinityThread,
+};
+use windows_kernel_sys::ntoskrnl::{ObDereferenceObject, ObReferenceObject, ObReferenceObjectByHandle};
+use windows_kernel_sys::ntoskrnl::{IoCreateSystemThread, PsGetThreadId, ZwClose};
+
+pub type ThreadId = usize;
+
+/// The lowest priority of a thread.
+pub const LOW_PRIORITY: KPRIORITY = 0;
+/// The lowest priority of a thread in the real-time priority class.
+pub const LOW_REALTIME_PRIORITY: KPRIORITY = 16;
+/// The highest priority of a thread.
+pub const HIGH_PRIORITY: KPRIORITY = 31;
+
+/// A referenced kernel thread object.
+pub struct Thread {
+    thread: PETHREAD,
+}
+
+unsafe impl Send for Thread {}
+unsafe impl Sync for Thread {}
+
+impl Thread {
+    /// Returns the thread that is currently running.
+    pub fn current() -> Self {
+        let thread = unsafe {
+            KeGetCurrentThread()
+        } as PETHREAD;
+
+        unsafe {
+            ObReferenceObject(thread as _);
+        }
+
+        Self {
+            thread,
+        }
+    }
+
+    pub fn as_ptr(&self) -> PETHREAD {
+        self.thread
+    }
+
+    /// Returns the unique identifier of the thread.
+    pub fn id(&self) -> ThreadId {
+        let handle = unsafe {
+            PsGetThreadId(self.thread)
+        };
+
+        handle as _
+    }
+
+    /// Returns the current priority of the thread.
+    pub fn priority(&self) -> KPRIORITY {
+        unsafe {
+            KeQueryPriorityThread(self.thread as _)
+        }
+    }
+
+    /// Sets the priority of the thread and returns the previous priority. Returns
+    /// [`Error::INVALID_PARAMETER`] if the priority is not in the range from [`LOW_PRIORITY`] to
+    /// [`HIGH_PRIORITY`]. Requires `IRQL <= DISPATCH_LEVEL`.
+    pub fn set_priority(&self, priority: KPRIORITY) -> Result<KPRIORITY, Error> {
+        if !(LOW_PRIORITY..=HIGH_PRIORITY).contains(&priority) {
+            return Err(Error::INVALID_PARAMETER);
+        }
+
+        Ok(unsafe {
+            KeSetPriorityThread(self.thread as _, priority)
+        })
+    }
+}
+
+impl Clone for Thread {
+    fn clone(&self) -> Self {
+        unsafe {
+            ObReferenceObject(self.thread as _);
+        }
+
+        Self {
+            thread: self.thread,
+        }
+    }
+}
+
+impl Drop for Thread {
+    fn drop(&mut self) {
+        unsafe {
+            ObDereferenceObject(self.thread as _);
+        }
+    }
+}
+
+/// Holds the result of the closure until the thread gets joined.
+struct Packet<T> {
+    result: UnsafeCell<Option<T>>,
+}
+
+// The result is written by the spawned thread before it terminates, and only read after waiting
+// for the thread to terminate.
+unsafe impl<T: Send> Sync for Packet<T> {}
+
+struct Start<F, T> {
+    f: F,
+    packet: Arc<Packet<T>>,
+    affinity: Option<GROUP_AFFINITY>,
+    priority: Option<KPRIORITY>,
+}
+
+/// This is the start routine used by [`Builder::spawn`] to run the closure.
+unsafe extern "C" fn thread_start<F, T>(context: PVOID)
+where
+    F: FnOnce() -> T,
+{
+    let Start { f, packet, affinity, priority } = *Box::from_raw(context as *mut Start<F, T>);
+
+    if let Some(mut affinity) = affinity {
+        KeSetSystemGroupAffinityThread(&mut affinity, core::ptr::null_mut());
+    }
+
+    if let Some(priority) = priority {
+        KeSetPriorityThread(KeGetCurrentThread(), priority);
+    }
+
+    *packet.result.get() = Some(f());
+}
+
+/// A builder for system threads, which can be used to configure the priority and the affinity
+/// of the thread before spawning it.
+///
+/// ```ignore
+/// let handle = thread::Builder::new()
+///     .priority(thread::LOW_REALTIME_PRIORITY)
+///     .affinity(0)
+///     .spawn(|| worker())?;
+/// ```
+#[derive(Default)]
+pub struct Builder {
+    priority: Option<KPRIORITY>,
+    affinity: Option<u32>,
+}
+
+impl Builder {
+    /// Creates a builder for a thread with the default priority and affinity.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the priority that the thread starts with, which must be in the range from
+    /// [`LOW_PRIORITY`] to [`HIGH_PRIORITY`].
+    pub fn priority(mut self, priority: KPRIORITY) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// Restricts the thread to the CPU with the given logical CPU number, as used by
+    /// [`run_on_cpu`].
+    ///
+    /// [`run_on_cpu`]: crate::affinity::run_on_cpu
+    pub fn affinity(mut self, cpu_num: u32) -> Self {
+        self.affinity = Some(cpu_num);
+        self
+    }
+
+    /// Spawns a system thread that runs the closure, and returns a [`JoinHandle`] for it. The
+    /// thread runs in the context of the system process at `PASSIVE_LEVEL`.
+    ///
+    /// The thread is created through `IoCreateSystemThread`, which keeps the driver object
+    /// referenced, and thereby the driver image loaded, until the thread terminates. Returns
+    /// [`Error::UNSUCCESSFUL`] if the driver object has not been recorded through
+    /// [`driver::init`]. This function can only be called at `PASSIVE_LEVEL`.
+    ///
+    /// [`driver::init`]: crate::driver::init
+    pub fn spawn<F, T>(self, f: F) -> Result<JoinHandle<T>, Error>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        crate::debug_assert_irql!(== PASSIVE_LEVEL);
+
+        if let Some(priority) = self.priority {
+            if !(LOW_PRIORITY..=HIGH_PRIORITY).contains(&priority) {
+                return Err(Error::INVALID_PARAMETER);
+            }
+        }
+
+        let affinity = match self.affinity {
+            Some(cpu_num) => {
+                let processor_num = get_processor_number(cpu_num)?;
+
+                Some(GROUP_AFFINITY {
+                    Mask: 1 << processor_num.Number,
+                    Group: processor_num.Group,
+                    Reserved: [0; 3],
+                })
+            }
+            _ => None,
+        };
+
+        let driver = driver_object();
+
+        if driver.is_null() {
+            return Err(Error::UNSUCCESSFUL);
+        }
+
+        let packet = Arc::new(Packet {
+            result: UnsafeCell::new(None),
+        });
+
+        let start = Box::into_raw(Box::new(Start {
+            f,
+            packet: packet.clone(),
+            affinity,
+            priority: self.priority,
+        }));
+
+        let mut attrs: OBJECT_ATTRIBUTES = unsafe { core::mem::zeroed() };
+        attrs.Length = core::mem::size_of::<OBJECT_ATTRIBUTES>() as u32;
+        attrs.Attributes = OBJ_KERNEL_HANDLE;
+
+        let mut handle: HANDLE = core::ptr::null_mut();
+
+        let result = unsafe {
+            IoCreateSystemThread(
+                driver as _,
+                &mut handle,
+                THREAD_ALL_ACCESS,
+                &mut attrs,
+                core::ptr::null_mut(),
+                core::ptr::null_mut(),
+                Some(thread_start::<F, T>),
+                start as _,
+            )
+        }.into_result();
+
+        if let Err(e) = result {
+            // The thread has not been created, hence the start context is still ours.
+            drop(unsafe { Box::from_raw(start) });
+            return Err(e);
+        }
+
+        let mut thread: PVOID = core::ptr::null_mut();
+
+        // The handle was just created by `IoCreateSystemThread`, hence there is no need to check
+        // the object type.
+        let result = unsafe {
+            ObReferenceObjectByHandle(
+                handle,
+                THREAD_ALL_ACCESS,
+                core::ptr::null_mut(),
+                MODE::KernelMode as _,
+                &mut thread,
+                core::ptr::null_mut(),
+            )
+        }.into_result();
+
+        unsafe {
+            ZwClose(handle);
+        }
+
+        result?;
+
+        Ok(JoinHandle {
+            thread: Thread {
+                thread: thread as _,
+            },
+            packet,
+        })
+    }
+}
+
+/// Spawns a system thread with the default priority and affinity that runs the closure, and
+/// returns a [`JoinHandle`] for it. See [`Builder::spawn`] for more details.
+///
+/// ```ignore
+/// let handle = thread::spawn(|| 42)?;
+///
+/// assert_eq!(handle.join(), 42);
+/// ```
+pub fn spawn<F, T>(f: F) -> Result<JoinHandle<T>, Error>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    Builder::new().spawn(f)
+}
+
+/// An owned permission to join on a thread, i.e. to wait for it to terminate and to retrieve the
+/// result of its closure. Dropping the handle detaches the thread, which keeps running.
+///
+/// The handle can also be passed to [`wait_any`] and [`wait_all`] to wait for multiple threads.
+///
+/// This structure is created by [`spawn`] and [`Builder::spawn`].
+///
+/// [`wait_any`]: crate::sync::wait::wait_any
+/// [`wait_all`]: crate::sync::wait::wait_all
+pub struct JoinHandle<T> {
+    thread: Thread,
+    packet: Arc<Packet<T>>,
+}
+
+impl<T> JoinHandle<T> {
+    /// Returns the thread.
+    pub fn thread(&self) -> &Thread {
+        &self.thread
+    }
+
+    /// Returns whether the thread has terminated. Can be called at `IRQL <= DISPATCH_LEVEL`.
+    pub fn is_finished(&self) -> bool {
+        let timeout = Timeout::Relative(Duration::ZERO);
+        let status = unsafe { wait_for_object(self.as_dispatcher_object(), Some(timeout)) };
+
+        status == STATUS_WAIT_0
+    }
+
+    /// Blocks the current thread until the thread terminates, and returns the result of its
+    /// closure. Can only be called at `IRQL <= APC_LEVEL`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the thread terminated without returning from its closure, e.g. by calling
+    /// `PsTerminateSystemThread`.
+    pub fn join(self) -> T {
+        self.wait();
+
+        // The thread has terminated, hence nobody else accesses the result.
+        unsafe { (*self.packet.result.get()).take() }
+            .expect("the thread terminated without returning a result")
+    }
+}
+
+unsafe impl<T> Waitable for JoinHandle<T> {
+    fn as_dispatcher_object(&self) -> PVOID {
+        self.thread.thread as _
+    }
+}
+
+/// Blocks the current thread for at least the given duration. Can only be called at
+/// `IRQL <= APC_LEVEL`.
+pub fn sleep(duration: Duration) {
+    let mut interval = Timeout::Relative(duration).as_large_integer();
+
+    unsafe {
+        KeDelayExecutionThread(
+            MODE::KernelMode as _,
+            false as _,
+            &mut interval,
+        );
+    }
+}
+
+/// Gives up the remainder of the time slice of the current thread, such that another thread that
+/// is ready to run on this CPU can run. Can only be called at `IRQL <= APC_LEVEL`.
+pub fn yield_now() {
+    sleep(Duration::ZERO);
+}
@@ -0,0 +1,492 @@
+//! This module provides clocks and timeouts. The kernel measures time in 100-nanosecond
+//! intervals, and this module converts between those intervals and [`Duration`].
+//!
+//! An [`Instant`] is based on the interrupt time, which counts the time since the system booted
+//! and never goes backwards. A [`SystemTime`] is based on the system time, which counts the time
+//! since January 1, 1601 (UTC), and may jump when the clock gets adjusted.
+
+use core::ops::{Add, AddAssign, Sub, SubAssign};
+use core::time::Duration;
+use windows_kernel_common::time::{absolute_timeout, relative_timeout};
+use windows_kernel_common::time::{checked_duration_to_intervals, unsigned_intervals_to_duration};
+use windows_kernel_sys::base::{LARGE_INTEGER, TIME_FIELDS};
+use windows_kernel_sys::ntoskrnl::{ExLocalTimeToSystemTime, ExSystemTimeToLocalTime};
+use windows_kernel_sys::ntoskrnl::{KeQueryInterruptTime, KeQueryInterruptTimePrecise};
+use windows_kernel_sys::ntoskrnl::{KeQueryPerformanceCounter, KeQuerySystemTimePrecise};
+use windows_kernel_sys::ntoskrnl::{RtlTimeFieldsToTime, RtlTimeToTimeFields};
+
+pub use windows_kernel_common::time::{duration_to_intervals, intervals_to_duration};
+pub use windows_kernel_common::time::INTERVALS_PER_SECOND;
+
+/// Converts the duration into a `LARGE_INTEGER` holding a number of 100-nanosecond intervals.
+pub fn duration_to_large_integer(duration: Duration) -> LARGE_INTEGER {
+    large_integer(duration_to_intervals(duration))
+}
+
+/// Converts a `LARGE_INTEGER` holding a number of 100-nanosecond intervals into a duration. As
+/// relative timeouts are negative, the sign of the number is ignored.
+pub fn large_integer_to_duration(value: LARGE_INTEGER) -> Duration {
+    intervals_to_duration(unsafe { value.QuadPart })
+}
+
+fn large_integer(value: i64) -> LARGE_INTEGER {
+    let mut result: LARGE_INTEGER = unsafe { core::mem::zeroed() };
+    result.QuadPart = value;
+    result
+}
+
+/// A measurement of the interrupt time, which is the time since the system booted, including
+/// the time the system spent in sleep or hibernation. Unlike a [`SystemTime`], the interrupt time
+/// is not affected by changes to the system clock, which makes it suitable for measuring how
+/// much time has passed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Instant {
+    intervals: u64,
+}
+
+impl Instant {
+    /// Returns the current interrupt time, as precise as the performance counter allows. Can be
+    /// called at any IRQL.
+    pub fn now() -> Self {
+        // This also returns the value of the performance counter the interrupt time is based on.
+        let mut count = 0;
+
+        Self {
+            intervals: unsafe {
+                KeQueryInterruptTimePrecise(
+                    &mut count,
+                )
+            },
+        }
+    }
+
+    /// Returns the current interrupt time, as of the last clock interrupt. This is cheaper than
+    /// [`Instant::now`], but only has the resolution of the system clock interval, which is
+    /// typically 15.6 milliseconds. Can be called at any IRQL.
+    pub fn now_coarse() -> Self {
+        Self {
+            intervals: unsafe { KeQueryInterruptTime() },
+        }
+    }
+
+    /// Creates an instant from an interrupt time in 100-nanosecond intervals, e.g. as returned by
+    /// `KeQueryInterruptTime`.
+    pub const fn from_intervals(intervals: u64) -> Self {
+        Self {
+            intervals,
+        }
+    }
+
+    /// Returns the interrupt time in 100-nanosecond intervals.
+    pub const fn as_intervals(&self) -> u64 {
+        self.intervals
+    }
+
+    /// Returns the amount of time that passed from the earlier instant to this one, or `None` if
+    /// the earlier instant is later than this one.
+    pub fn checked_duration_since(&self, earlier: Instant) -> Option<Duration> {
+        self.intervals.checked_sub(earlier.intervals)
+            .map(unsigned_intervals_to_duration)
+    }
+
+    /// Returns the amount of time that passed from the earlier instant to this one, or zero if
+    /// the earlier instant is later than this one.
+    pub fn saturating_duration_since(&self, earlier: Instant) -> Duration {
+        self.checked_duration_since(earlier)
+            .unwrap_or_default()
+    }
+
+    /// Returns the amount of time that passed from the earlier instant to this one, or zero if
+    /// the earlier instant is later than this one.
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        self.saturating_duration_since(earlier)
+    }
+
+    /// Returns the amount of time that passed since this instant.
+    pub fn elapsed(&self) -> Duration {
+        Instant::now().saturating_duration_since(*self)
+    }
+
+    /// Returns the instant that is the given duration later, or `None` if it cannot be
+    /// represented.
+    pub fn checked_add(&self, duration: Duration) -> Option<Instant> {
+        let intervals = checked_duration_to_intervals(duration)?;
+
+        self.intervals.checked_add(intervals)
+            .map(Instant::from_intervals)
+    }
+
+    /// Returns the instant that is the given duration earlier, or `None` if it cannot be
+    /// represented.
+    pub fn checked_sub(&self, duration: Duration) -> Option<Instant> {
+        let intervals = checked_duration_to_intervals(duration)?;
+
+        self.intervals.checked_sub(intervals)
+            .map(Instant::from_intervals)
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    fn add(self, duration: Duration) -> Instant {
+        self.checked_add(duration)
+            .expect("overflow when adding duration to instant")
+    }
+}
+
+impl AddAssign<Duration> for Instant {
+    fn add_assign(&mut self, duration: Duration) {
+        *self = *self + duration;
+    }
+}
+
+impl Sub<Duration> for Instant {
+    type Output = Instant;
+
+    fn sub(self, duration: Duration) -> Instant {
+        self.checked_sub(duration)
+            .expect("overflow when subtracting duration from instant")
+    }
+}
+
+impl SubAssign<Duration> for Instant {
+    fn sub_assign(&mut self, duration: Duration) {
+        *self = *self - duration;
+    }
+}
+
+impl Sub<Instant> for Instant {
+    type Output = Duration;
+
+    fn sub(self, earlier: Instant) -> Duration {
+        self.saturating_duration_since(earlier)
+    }
+}
+
+/// A raw reading of the performance counter, which is the clock with the highest resolution
+/// available. The frequency of the counter is fixed at boot, and is typically 10 MHz.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PerformanceCounter {
+    count: u64,
+    frequency: u64,
+}
+
+impl PerformanceCounter {
+    /// Reads the performance counter through `KeQueryPerformanceCounter`. Can be called at any
+    /// IRQL.
+    pub fn now() -> Self {
+        let mut frequency = large_integer(0);
+
+        let count = unsafe {
+            KeQueryPerformanceCounter(
+                &mut frequency,
+            )
+        };
+
+        Self {
+            count: unsafe { count.QuadPart } as u64,
+            frequency: unsafe { frequency.QuadPart } as u64,
+        }
+    }
+
+    /// Returns the value of the counter in ticks.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns the number of ticks per second.
+    pub fn frequency(&self) -> u64 {
+        self.frequency
+    }
+
+    /// Returns the amount of time that passed from the earlier reading to this one, or zero if
+    /// the earlier reading is later than this one.
+    pub fn duration_since(&self, earlier: PerformanceCounter) -> Duration {
+        let ticks = self.count.saturating_sub(earlier.count) as u128;
+        let nanos = ticks * 1_000_000_000 / self.frequency.max(1) as u128;
+
+        Duration::new(
+            (nanos / 1_000_000_000).min(u64::MAX as u128) as u64,
+            (nanos % 1_000_000_000) as u32,
+        )
+    }
+
+    /// Returns the amount of time that passed since this reading.
+    pub fn elapsed(&self) -> Duration {
+        PerformanceCounter::now().duration_since(*self)
+    }
+}
+
+/// A measurement of the system time, which is the number of 100-nanosecond intervals since
+/// January 1, 1601 (UTC). The system time may jump forwards or backwards when the clock gets
+/// adjusted, use an [`Instant`] to measure how much time has passed instead.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SystemTime {
+    intervals: u64,
+}
+
+impl SystemTime {
+    /// The system time of January 1, 1970 (UTC).
+    pub const UNIX_EPOCH: SystemTime = SystemTime::from_intervals(116_444_736_000_000_000);
+
+    /// Returns the current system time, as precise as the performance counter allows. Can be
+    /// called at any IRQL.
+    pub fn now() -> Self {
+        let mut time = large_integer(0);
+
+        unsafe {
+            KeQuerySystemTimePrecise(
+                &mut time,
+            )
+        };
+
+        Self::from_large_integer(time)
+    }
+
+    /// Creates a system time from a number of 100-nanosecond intervals since January 1, 1601
+    /// (UTC).
+    pub const fn from_intervals(intervals: u64) -> Self {
+        Self {
+            intervals,
+        }
+    }
+
+    /// Returns the number of 100-nanosecond intervals since January 1, 1601 (UTC).
+    pub const fn as_intervals(&self) -> u64 {
+        self.intervals
+    }
+
+    /// Creates a system time from a `LARGE_INTEGER`, e.g. the `CreationTime` of a file. Negative
+    /// values are clamped to January 1, 1601.
+    pub fn from_large_integer(value: LARGE_INTEGER) -> Self {
+        Self::from_intervals(unsafe { value.QuadPart }.max(0) as u64)
+    }
+
+    /// Returns the system time as a `LARGE_INTEGER`, e.g. to set the `CreationTime` of a file.
+    pub fn as_large_integer(&self) -> LARGE_INTEGER {
+        large_integer(self.intervals.min(i64::MAX as u64) as i64)
+    }
+
+    /// Returns the amount of time that passed from the earlier system time to this one, or `None`
+    /// if the earlier system time is later than this one.
+    pub fn duration_since(&self, earlier: SystemTime) -> Option<Duration> {
+        self.intervals.checked_sub(earlier.intervals)
+            .map(unsigned_intervals_to_duration)
+    }
+
+    /// Returns the amount of time that passed since this system time, or `None` if the system
+    /// clock has been adjusted to before this system time.
+    pub fn elapsed(&self) -> Option<Duration> {
+        SystemTime::now().duration_since(*self)
+    }
+
+    /// Returns the system time that is the given duration later, or `None` if it cannot be
+    /// represented.
+    pub fn checked_add(&self, duration: Duration) -> Option<SystemTime> {
+        let intervals = checked_duration_to_intervals(duration)?;
+
+        self.intervals.checked_add(intervals)
+            .map(SystemTime::from_intervals)
+    }
+
+    /// Returns the system time that is the given duration earlier, or `None` if it cannot be
+    /// represented.
+    pub fn checked_sub(&self, duration: Duration) -> Option<SystemTime> {
+        let intervals = checked_duration_to_intervals(duration)?;
+
+        self.intervals.checked_sub(intervals)
+            .map(SystemTime::from_intervals)
+    }
+
+    /// Splits the system time into its calendar fields in UTC.
+    pub fn to_time_fields(&self) -> TimeFields {
+        let mut time = self.as_large_integer();
+        let mut fields: TIME_FIELDS = unsafe { core::mem::zeroed() };
+
+        unsafe {
+            RtlTimeToTimeFields(
+                &mut time,
+                &mut fields,
+            )
+        };
+
+        TimeFields::from_raw(&fields)
+    }
+
+    /// Splits the system time into its calendar fields in the local time zone of the system,
+    /// using `ExSystemTimeToLocalTime`.
+    pub fn to_local_time_fields(&self) -> TimeFields {
+        let mut time = self.as_large_integer();
+        let mut local_time = large_integer(0);
+
+        unsafe {
+            ExSystemTimeToLocalTime(
+                &mut time,
+                &mut local_time,
+            )
+        };
+
+        SystemTime::from_large_integer(local_time)
+            .to_time_fields()
+    }
+
+    /// Creates a system time from calendar fields in UTC. The weekday is ignored. Returns `None`
+    /// if the fields do not describe a valid date and time.
+    pub fn from_time_fields(fields: &TimeFields) -> Option<Self> {
+        let mut fields = fields.to_raw();
+        let mut time = large_integer(0);
+
+        let valid = unsafe {
+            RtlTimeFieldsToTime(
+                &mut fields,
+                &mut time,
+            )
+        } != 0;
+
+        match valid {
+            true => Some(SystemTime::from_large_integer(time)),
+            _ => None,
+        }
+    }
+
+    /// Creates a system time from calendar fields in the local time zone of the system, using
+    /// `ExLocalTimeToSystemTime`. The weekday is ignored. Returns `None` if the fields do not
+    /// describe a valid date and time.
+    pub fn from_local_time_fields(fields: &TimeFields) -> Option<Self> {
+        let mut local_time = SystemTime::from_time_fields(fields)?
+            .as_large_integer();
+        let mut time = large_integer(0);
+
+        unsafe {
+            ExLocalTimeToSystemTime(
+                &mut local_time,
+                &mut time,
+            )
+        };
+
+        Some(SystemTime::from_large_integer(time))
+    }
+}
+
+impl Add<Duration> for SystemTime {
+    type Output = SystemTime;
+
+    fn add(self, duration: Duration) -> SystemTime {
+        self.checked_add(duration)
+            .expect("overflow when adding duration to system time")
+    }
+}
+
+impl AddAssign<Duration> for SystemTime {
+    fn add_assign(&mut self, duration: Duration) {
+        *self = *self + duration;
+    }
+}
+
+impl Sub<Duration> for SystemTime {
+    type Output = SystemTime;
+
+    fn sub(self, duration: Duration) -> SystemTime {
+        self.checked_sub(duration)
+            .expect("overflow when subtracting duration from system time")
+    }
+}
+
+impl SubAssign<Duration> for SystemTime {
+    fn sub_assign(&mut self, duration: Duration) {
+        *self = *self - duration;
+    }
+}
+
+/// The calendar fields of a [`SystemTime`], as filled in by `RtlTimeToTimeFields`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeFields {
+    /// The year, starting at 1601.
+    pub year: u16,
+    /// The month, from 1 to 12.
+    pub month: u8,
+    /// The day of the month, from 1 to 31.
+    pub day: u8,
+    /// The hour, from 0 to 23.
+    pub hour: u8,
+    /// The minute, from 0 to 59.
+    pub minute: u8,
+    /// The second, from 0 to 59.
+    pub second: u8,
+    /// The millisecond, from 0 to 999.
+    pub millisecond: u16,
+    /// The day of the week, from 0 (Sunday) to 6 (Saturday).
+    pub weekday: u8,
+}
+
+impl TimeFields {
+    fn from_raw(fields: &TIME_FIELDS) -> Self {
+        Self {
+            year: fields.Year as _,
+            month: fields.Month as _,
+            day: fields.Day as _,
+            hour: fields.Hour as _,
+            minute: fields.Minute as _,
+            second: fields.Second as _,
+            millisecond: fields.Milliseconds as _,
+            weekday: fields.Weekday as _,
+        }
+    }
+
+    fn to_raw(&self) -> TIME_FIELDS {
+        TIME_FIELDS {
+            Year: self.year as _,
+            Month: self.month as _,
+            Day: self.day as _,
+            Hour: self.hour as _,
+            Minute: self.minute as _,
+            Second: self.second as _,
+            Milliseconds: self.millisecond as _,
+            Weekday: self.weekday as _,
+        }
+    }
+}
+
+/// A timeout for the wait functions, which either expires after a duration, or at a system time.
+///
+/// A relative timeout keeps running while the system is asleep, but is not affected by changes to
+/// the system clock. An absolute timeout expires once the system clock reaches the system time,
+/// and therefore follows changes to the system clock.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Timeout {
+    /// Expires once the duration has passed.
+    Relative(Duration),
+    /// Expires once the system clock reaches the system time.
+    Absolute(SystemTime),
+}
+
+impl Timeout {
+    /// Returns a relative timeout that expires once the instant has been reached, or immediately
+    /// if the instant already passed.
+    pub fn until(deadline: Instant) -> Self {
+        Timeout::Relative(deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Converts the timeout into the representation used by the wait functions, i.e. a negative
+    /// number of 100-nanosecond intervals for a relative timeout, and a positive system time for
+    /// an absolute timeout.
+    pub fn as_large_integer(&self) -> LARGE_INTEGER {
+        match self {
+            Timeout::Relative(duration) => large_integer(relative_timeout(*duration)),
+            Timeout::Absolute(time) => large_integer(absolute_timeout(time.as_intervals())),
+        }
+    }
+}
+
+impl From<Duration> for Timeout {
+    fn from(duration: Duration) -> Self {
+        Timeout::Relative(duration)
+    }
+}
+
+impl From<SystemTime> for Timeout {
+    fn from(time: SystemTime) -> Self {
+        Timeout::Absolute(time)
+    }
+}
@@ -0,0 +1,240 @@
+//! This module provides kernel timers, which run a closure at `DISPATCH_LEVEL` once or
+//! periodically after a given amount of time.
+
+use alloc::boxed::Box;
+use core::cell::UnsafeCell;
+use core::time::Duration;
+use crate::dpc::Dpc;
+use crate::error::Error;
+use crate::sync::wait::Waitable;
+use crate::time::{duration_to_intervals, Timeout};
+use windows_kernel_sys::base::{EX_TIMER_HIGH_RESOLUTION, KTIMER, PEX_TIMER, PVOID};
+use windows_kernel_sys::ntoskrnl::{KeCancelTimer, KeInitializeTimer, KeSetTimerEx};
+use windows_kernel_sys::ntoskrnl::{ExAllocateTimer, ExCancelTimer, ExDeleteTimer, ExSetTimer};
+use windows_kernel_sys::ntoskrnl::KeFlushQueuedDpcs;
+
+/// A timer built on top of a `KTIMER`, which queues a [`Dpc`] that runs the closure once the
+/// timer expires, either once or periodically. The resolution of the timer is limited by the
+/// system clock interval, which is typically 15.6 milliseconds. See [`HighResolutionTimer`] for a
+/// timer with a higher resolution.
+///
+/// Threads can also wait for the timer to expire through [`Waitable`].
+///
+/// Cancelling or dropping the timer waits for the closure to finish, which requires
+/// `IRQL == PASSIVE_LEVEL`.
+pub struct Timer {
+    // The `KTIMER` is linked into the timer table while it is set, hence it must not move.
+    timer: Box<UnsafeCell<KTIMER>>,
+    dpc: Dpc,
+}
+
+unsafe impl Send for Timer {}
+unsafe impl Sync for Timer {}
+
+impl Timer {
+    /// Creates a timer that runs the closure at `DISPATCH_LEVEL` whenever it expires. The timer
+    /// is not set.
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        let timer: Box<UnsafeCell<KTIMER>> = Box::new(UnsafeCell::new(unsafe {
+            core::mem::zeroed()
+        }));
+
+        unsafe {
+            KeInitializeTimer(
+                timer.get(),
+            )
+        };
+
+        Self {
+            timer,
+            dpc: Dpc::new(f),
+        }
+    }
+
+    /// Sets the timer to expire once after the given duration, or at the given system time,
+    /// replacing any previous setting of the timer. Returns `true` if the timer was already set.
+    /// Can be called at `IRQL <= DISPATCH_LEVEL`.
+    pub fn schedule(&self, due: impl Into<Timeout>) -> bool {
+        unsafe {
+            KeSetTimerEx(
+                self.timer.get(),
+                due.into().as_large_integer(),
+                0,
+                self.dpc.as_ptr(),
+            ) != 0
+        }
+    }
+
+    /// Sets the timer to expire after the given duration, or at the given system time, and then
+    /// every period, replacing any previous setting of the timer. The period is rounded to
+    /// milliseconds, and is at least one millisecond. Returns `true` if the timer was already set.
+    /// Can be called at `IRQL <= DISPATCH_LEVEL`.
+    pub fn schedule_periodic(&self, due: impl Into<Timeout>, period: Duration) -> bool {
+        let period = period.as_millis().clamp(1, i32::MAX as u128) as i32;
+
+        unsafe {
+            KeSetTimerEx(
+                self.timer.get(),
+                due.into().as_large_integer(),
+                period,
+                self.dpc.as_ptr(),
+            ) != 0
+        }
+    }
+
+    /// Cancels the timer, and waits for the closure to finish on any processor it is running on.
+    /// Returns `true` if the timer was set. Can only be called at `PASSIVE_LEVEL`.
+    pub fn cancel(&self) -> bool {
+        crate::debug_assert_irql!(== PASSIVE_LEVEL);
+
+        let cancelled = unsafe {
+            KeCancelTimer(
+                self.timer.get(),
+            )
+        } != 0;
+
+        self.dpc.cancel();
+
+        cancelled
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        // Dropping the DPC afterwards waits for the closure to finish.
+        unsafe {
+            KeCancelTimer(
+                self.timer.get(),
+            )
+        };
+    }
+}
+
+unsafe impl Waitable for Timer {
+    fn as_dispatcher_object(&self) -> PVOID {
+        self.timer.get() as _
+    }
+}
+
+/// This is the callback used by [`HighResolutionTimer`] to run the closure.
+unsafe extern "C" fn timer_callback(
+    _timer: PEX_TIMER,
+    context: PVOID,
+) {
+    let f = &*(context as *const Box<dyn Fn() + Send + Sync>);
+
+    f();
+}
+
+/// A high-resolution timer built on top of `ExAllocateTimer`, which runs the closure at
+/// `DISPATCH_LEVEL` once the timer expires, either once or periodically. Unlike a [`Timer`], the
+/// timer expires as close to the due time as the hardware allows, at the expense of more
+/// frequent clock interrupts while the timer is set.
+///
+/// Cancelling or dropping the timer waits for the closure to finish, which requires
+/// `IRQL == PASSIVE_LEVEL`.
+pub struct HighResolutionTimer {
+    timer: PEX_TIMER,
+    // The timer holds a pointer to the closure, hence it must not move.
+    _closure: Box<Box<dyn Fn() + Send + Sync>>,
+}
+
+unsafe impl Send for HighResolutionTimer {}
+unsafe impl Sync for HighResolutionTimer {}
+
+impl HighResolutionTimer {
+    /// Creates a high-resolution timer that runs the closure at `DISPATCH_LEVEL` whenever it
+    /// expires. The timer is not set. Returns [`Error::INSUFFICIENT_RESOURCES`] if the timer could
+    /// not be allocated.
+    pub fn new<F>(f: F) -> Result<Self, Error>
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        let f: Box<Box<dyn Fn() + Send + Sync>> = Box::new(Box::new(f));
+
+        let timer = unsafe {
+            ExAllocateTimer(
+                Some(timer_callback),
+                &*f as *const _ as PVOID,
+                EX_TIMER_HIGH_RESOLUTION,
+            )
+        };
+
+        if timer.is_null() {
+            return Err(Error::INSUFFICIENT_RESOURCES);
+        }
+
+        Ok(Self {
+            timer,
+            _closure: f,
+        })
+    }
+
+    /// Sets the timer to expire once after the given duration, or at the given system time,
+    /// replacing any previous setting of the timer. Returns `true` if the timer was already set.
+    /// Can be called at `IRQL <= DISPATCH_LEVEL`.
+    pub fn schedule(&self, due: impl Into<Timeout>) -> bool {
+        unsafe {
+            ExSetTimer(
+                self.timer,
+                due.into().as_large_integer().QuadPart,
+                0,
+                core::ptr::null_mut(),
+            ) != 0
+        }
+    }
+
+    /// Sets the timer to expire after the given duration, or at the given system time, and then
+    /// every period, replacing any previous setting of the timer. Returns `true` if the timer was
+    /// already set. Can be called at `IRQL <= DISPATCH_LEVEL`.
+    pub fn schedule_periodic(&self, due: impl Into<Timeout>, period: Duration) -> bool {
+        unsafe {
+            ExSetTimer(
+                self.timer,
+                due.into().as_large_integer().QuadPart,
+                duration_to_intervals(period).max(1),
+                core::ptr::null_mut(),
+            ) != 0
+        }
+    }
+
+    /// Cancels the timer, and waits for the closure to finish on any processor it is running on.
+    /// Returns `true` if the timer was set. Can only be called at `PASSIVE_LEVEL`.
+    pub fn cancel(&self) -> bool {
+        crate::debug_assert_irql!(== PASSIVE_LEVEL);
+
+        let cancelled = unsafe {
+            ExCancelTimer(
+                self.timer,
+                core::ptr::null_mut(),
+            )
+        } != 0;
+
+        // The closure runs in a DPC, hence this waits for the closure to finish.
+        unsafe {
+            KeFlushQueuedDpcs()
+        };
+
+        cancelled
+    }
+}
+
+impl Drop for HighResolutionTimer {
+    fn drop(&mut self) {
+        crate::debug_assert_irql!(== PASSIVE_LEVEL);
+
+        // This cancels the timer and waits for the closure to finish, before the closure gets
+        // dropped.
+        unsafe {
+            ExDeleteTimer(
+                self.timer,
+                true as _,
+                true as _,
+                core::ptr::null_mut(),
+            )
+        };
+    }
+}
@@ -1,30 +1,64 @@
-use crate::error::Error;
+use crate::error::{Error, IntoResult};
+use crate::mdl::AccessMode;
+use crate::pod::{slice_as_bytes, Pod, Reader, Writer};
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use windows_kernel_sys::ntoskrnl::{CopyFromUser, CopyToUser};
 
-pub enum UserPtr {
+/// The buffers of a request. The buffers are borrowed from the request, as they are only valid
+/// until the request has been completed.
+pub enum UserPtr<'a> {
     Buffered {
         ptr: *mut cty::c_void,
         read_size: usize,
         write_size: usize,
+        _marker: PhantomData<&'a ()>,
     },
     Direct {
         read_ptr: *const cty::c_void,
         write_ptr: *mut cty::c_void,
         read_size: usize,
         write_size: usize,
+        _marker: PhantomData<&'a ()>,
+    },
+    /// The buffers of requests that use neither buffered nor direct I/O. These point directly to
+    /// the memory of the requestor, which may be unmapped or changed at any point in time, hence
+    /// these buffers are not exposed as slices. Instead [`read`], [`write`], [`read_into`] and
+    /// [`write_from`] probe the buffers if the request originates from user mode, and copy the
+    /// data while catching any exceptions.
+    ///
+    /// The addresses are only valid in the context of the process of the requesting thread, i.e.
+    /// while the request is being dispatched in the context of that thread. Hence the buffers
+    /// are empty for requests that have been marked as pending, e.g. through a
+    /// [`PendingRequest`], as these may be completed from any thread. Drivers that need to access
+    /// the buffers of a pending request should use buffered or direct I/O instead.
+    ///
+    /// [`read`]: UserPtr::read
+    /// [`write`]: UserPtr::write
+    /// [`read_into`]: UserPtr::read_into
+    /// [`write_from`]: UserPtr::write_from
+    /// [`PendingRequest`]: crate::request::PendingRequest
+    Neither {
+        read_ptr: *const cty::c_void,
+        write_ptr: *mut cty::c_void,
+        read_size: usize,
+        write_size: usize,
+        mode: AccessMode,
+        _marker: PhantomData<&'a ()>,
     },
-    Neither,
 }
 
-impl UserPtr {
+impl<'a> UserPtr<'a> {
     pub unsafe fn new_buffered(
         ptr: *mut cty::c_void,
         read_size: usize,
         write_size: usize,
     ) -> Self {
-        Self::Buffered{
+        Self::Buffered {
             ptr,
             read_size,
             write_size,
+            _marker: PhantomData,
         }
     }
 
@@ -39,18 +73,32 @@ impl UserPtr {
             write_ptr,
             read_size,
             write_size,
+            _marker: PhantomData,
         }
     }
 
-    pub unsafe fn new_neither() -> Self {
-        Self::Neither
+    pub unsafe fn new_neither(
+        read_ptr: *const cty::c_void,
+        write_ptr: *mut cty::c_void,
+        read_size: usize,
+        write_size: usize,
+        mode: AccessMode,
+    ) -> Self {
+        Self::Neither {
+            read_ptr,
+            write_ptr,
+            read_size,
+            write_size,
+            mode,
+            _marker: PhantomData,
+        }
     }
 
     pub fn read_size(&self) -> usize {
         match self {
             Self::Buffered { read_size, .. } => *read_size,
             Self::Direct { read_size, .. } => *read_size,
-            Self::Neither => 0,
+            Self::Neither { read_size, .. } => *read_size,
         }
     }
 
@@ -58,15 +106,16 @@ impl UserPtr {
         match self {
             Self::Buffered { write_size, .. } => *write_size,
             Self::Direct { write_size, .. } => *write_size,
-            Self::Neither => 0,
+            Self::Neither { write_size, .. } => *write_size,
         }
     }
 
+    /// Returns the input buffer as a slice. This is always empty for [`UserPtr::Neither`].
     pub fn as_slice(&self) -> &[u8] {
         let (ptr, size) = match self {
             Self::Buffered { ptr, read_size, .. } => (*ptr as _, *read_size),
             Self::Direct { read_ptr, read_size, .. } => (*read_ptr, *read_size),
-            Self::Neither => (core::ptr::null(), 0),
+            Self::Neither { .. } => (core::ptr::null(), 0),
         };
 
         if ptr.is_null() || size == 0 {
@@ -78,11 +127,12 @@ impl UserPtr {
         }
     }
 
+    /// Returns the output buffer as a slice. This is always empty for [`UserPtr::Neither`].
     pub fn as_mut_slice(&mut self) -> &mut [u8] {
         let (ptr, size) = match self {
             Self::Buffered { ptr, write_size, .. } => (*ptr, *write_size),
             Self::Direct { write_ptr, write_size, .. } => (*write_ptr, *write_size),
-            Self::Neither => (core::ptr::null_mut(), 0),
+            Self::Neither { .. } => (core::ptr::null_mut(), 0),
         };
 
         if ptr.is_null() || size == 0 {
@@ -94,11 +144,12 @@ impl UserPtr {
         }
     }
 
-    pub fn read<T: Copy + Default>(&self) -> Result<T, Error> {
+    /// Reads a single value from the input buffer. The value does not have to be aligned.
+    pub fn read<T: Pod>(&self) -> Result<T, Error> {
         let (ptr, size) = match self {
             Self::Buffered { ptr, read_size, .. } => (*ptr as _, *read_size),
             Self::Direct { read_ptr, read_size, .. } => (*read_ptr, *read_size),
-            Self::Neither => (core::ptr::null(), 0),
+            Self::Neither { read_ptr, read_size, .. } => (*read_ptr, *read_size),
         };
 
         if ptr.is_null() || size == 0 {
@@ -109,24 +160,31 @@ impl UserPtr {
             return Err(Error::INVALID_USER_BUFFER);
         }
 
-        let mut obj = T::default();
+        let mut obj = core::mem::MaybeUninit::<T>::uninit();
 
-        unsafe {
-            core::ptr::copy_nonoverlapping(
-                ptr as _,
-                &mut obj,
-                1,
-            );
+        if let Self::Neither { mode, .. } = self {
+            unsafe {
+                copy_from_user(obj.as_mut_ptr() as _, ptr, core::mem::size_of::<T>(), *mode)?;
+            }
+        } else {
+            unsafe {
+                core::ptr::copy_nonoverlapping(
+                    ptr as *const u8,
+                    obj.as_mut_ptr() as *mut u8,
+                    core::mem::size_of::<T>(),
+                );
+            }
         }
 
-        Ok(obj)
+        Ok(unsafe { obj.assume_init() })
     }
 
-    pub fn write<T: Copy>(&mut self, obj: &T) -> Result<(), Error> {
+    /// Writes a single value to the output buffer. The value is written unaligned.
+    pub fn write<T: Pod>(&mut self, obj: &T) -> Result<(), Error> {
         let (ptr, size) = match self {
             Self::Buffered { ptr, write_size, .. } => (*ptr, *write_size),
             Self::Direct { write_ptr, write_size, .. } => (*write_ptr, *write_size),
-            Self::Neither => (core::ptr::null_mut(), 0),
+            Self::Neither { write_ptr, write_size, .. } => (*write_ptr, *write_size),
         };
 
         if ptr.is_null() || size == 0 {
@@ -137,14 +195,148 @@ impl UserPtr {
             return Err(Error::INVALID_USER_BUFFER);
         }
 
+        if let Self::Neither { mode, .. } = self {
+            return unsafe {
+                copy_to_user(ptr, obj as *const T as _, core::mem::size_of::<T>(), *mode)
+            };
+        }
+
         unsafe {
             core::ptr::copy_nonoverlapping(
-                obj,
-                ptr as _,
-                1,
+                obj as *const T as *const u8,
+                ptr as *mut u8,
+                core::mem::size_of::<T>(),
             );
         }
 
         Ok(())
     }
+
+    /// Reads the input buffer as an array of values. Any trailing bytes that do not make up a
+    /// whole value are ignored.
+    pub fn read_vec<T: Pod>(&self) -> Result<Vec<T>, Error> {
+        let size = core::mem::size_of::<T>();
+
+        if size == 0 {
+            return Err(Error::INVALID_PARAMETER);
+        }
+
+        let count = self.read_size() / size;
+
+        match self {
+            Self::Neither { .. } => {
+                // The size is controlled by the requestor, so avoid running out of memory.
+                let mut buffer = Vec::new();
+                buffer.try_reserve_exact(count * size).map_err(|_| Error::INSUFFICIENT_RESOURCES)?;
+                buffer.resize(count * size, 0u8);
+
+                let copied = self.read_into(&mut buffer)?;
+
+                Ok(Reader::new(&buffer[..copied]).read_vec(copied / size)?)
+            }
+            _ => Ok(self.reader().read_vec(count)?),
+        }
+    }
+
+    /// Writes as many of the values to the output buffer as fit, and returns the number of bytes
+    /// written, such that the request can be completed with a partial output.
+    pub fn write_slice<T: Pod>(&mut self, values: &[T]) -> Result<usize, Error> {
+        let size = core::mem::size_of::<T>();
+
+        if size == 0 {
+            return Ok(0);
+        }
+
+        let count = values.len().min(self.write_size() / size);
+
+        self.write_from(slice_as_bytes(&values[..count]))
+    }
+
+    /// Returns a cursor over the input buffer. This is always empty for [`UserPtr::Neither`], use
+    /// [`read_into`] to copy the input buffer first.
+    ///
+    /// [`read_into`]: UserPtr::read_into
+    pub fn reader(&self) -> Reader<'_> {
+        Reader::new(self.as_slice())
+    }
+
+    /// Returns a cursor over the output buffer. This is always empty for [`UserPtr::Neither`],
+    /// use [`write_from`] instead.
+    ///
+    /// [`write_from`]: UserPtr::write_from
+    pub fn writer(&mut self) -> Writer<'_> {
+        Writer::new(self.as_mut_slice())
+    }
+
+    /// Copies the input buffer into the given buffer. Returns the number of bytes copied, which
+    /// is the smaller of the size of the input buffer and the size of the given buffer.
+    pub fn read_into(&self, buffer: &mut [u8]) -> Result<usize, Error> {
+        if let Self::Neither { read_ptr, read_size, mode, .. } = self {
+            let size = core::cmp::min(*read_size, buffer.len());
+
+            if read_ptr.is_null() || size == 0 {
+                return Ok(0);
+            }
+
+            unsafe {
+                copy_from_user(buffer.as_mut_ptr() as _, *read_ptr, size, *mode)?;
+            }
+
+            return Ok(size);
+        }
+
+        let data = self.as_slice();
+        let size = core::cmp::min(data.len(), buffer.len());
+
+        buffer[..size].copy_from_slice(&data[..size]);
+
+        Ok(size)
+    }
+
+    /// Copies the given buffer into the output buffer. Returns the number of bytes copied, which
+    /// is the smaller of the size of the output buffer and the size of the given buffer.
+    pub fn write_from(&mut self, buffer: &[u8]) -> Result<usize, Error> {
+        if let Self::Neither { write_ptr, write_size, mode, .. } = self {
+            let size = core::cmp::min(*write_size, buffer.len());
+
+            if write_ptr.is_null() || size == 0 {
+                return Ok(0);
+            }
+
+            unsafe {
+                copy_to_user(*write_ptr, buffer.as_ptr() as _, size, *mode)?;
+            }
+
+            return Ok(size);
+        }
+
+        let data = self.as_mut_slice();
+        let size = core::cmp::min(data.len(), buffer.len());
+
+        data[..size].copy_from_slice(&buffer[..size]);
+
+        Ok(size)
+    }
+}
+
+/// Copies memory from the requestor, where the memory is first probed through `ProbeForRead` if
+/// the requestor is in user mode. Any exceptions are turned into an [`Error`].
+unsafe fn copy_from_user(
+    dst: *mut cty::c_void,
+    src: *const cty::c_void,
+    size: usize,
+    mode: AccessMode,
+) -> Result<(), Error> {
+    CopyFromUser(dst, src as _, size as _, (mode == AccessMode::UserMode) as _).into_result()
+}
+
+/// Copies memory to the requestor, where the memory is first probed through `ProbeForWrite` if
+/// the requestor is in user mode. Any exceptions are turned into an [`Error`].
+unsafe fn copy_to_user(
+    dst: *mut cty::c_void,
+    src: *const cty::c_void,
+    size: usize,
+    mode: AccessMode,
+) -> Result<(), Error> {
+    CopyToUser(dst, src as _, size as _, (mode == AccessMode::UserMode) as _).into_result()
 }
@@ -0,0 +1,106 @@
+//! This module provides functions to defer work to a system worker thread, e.g. to run code at
+//! `PASSIVE_LEVEL` from a completion routine or a DPC.
+
+use alloc::boxed::Box;
+use crate::device::Device;
+use crate::error::Error;
+use crate::sync::rundown::{RundownGuard, DRIVER_RUNDOWN};
+use windows_kernel_sys::base::{PIO_WORKITEM, PVOID};
+use windows_kernel_sys::base::_WORK_QUEUE_TYPE as WORK_QUEUE_TYPE;
+use windows_kernel_sys::ntoskrnl::{IoAllocateWorkItem, IoFreeWorkItem, IoQueueWorkItemEx};
+
+/// The queue of system worker threads that a work item is queued to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum WorkQueue {
+    /// The queue for work items that are not time-critical. This is the queue that should be used
+    /// by most drivers.
+    Delayed,
+    /// The queue for time-critical work items, which is served by threads with a real-time
+    /// priority.
+    Critical,
+    /// The queue for work items that must run as soon as possible. This queue is reserved for
+    /// system components and should not be used by drivers.
+    HyperCritical,
+}
+
+impl WorkQueue {
+    pub(crate) fn as_raw(self) -> WORK_QUEUE_TYPE::Type {
+        match self {
+            WorkQueue::Delayed => WORK_QUEUE_TYPE::DelayedWorkQueue,
+            WorkQueue::Critical => WORK_QUEUE_TYPE::CriticalWorkQueue,
+            WorkQueue::HyperCritical => WORK_QUEUE_TYPE::HyperCriticalWorkQueue,
+        }
+    }
+}
+
+struct Context<F> {
+    f: F,
+    // Keeps the driver from being unloaded while the work item runs code of the driver.
+    _rundown: RundownGuard<'static>,
+}
+
+/// This is the worker routine used by [`queue`] to run the closure.
+unsafe extern "C" fn work_item_routine<F>(
+    _io_object: PVOID,
+    context: PVOID,
+    work_item: PIO_WORKITEM,
+)
+where
+    F: FnOnce(),
+{
+    let context = Box::from_raw(context as *mut Context<F>);
+    let Context { f, _rundown } = *context;
+
+    f();
+
+    // The work item can be freed from within the worker routine, as the I/O manager keeps the
+    // device object referenced until the worker routine has returned.
+    IoFreeWorkItem(work_item);
+}
+
+/// Queues the closure to run on a system worker thread at `PASSIVE_LEVEL`, e.g. to defer work
+/// from a completion routine or a DPC.
+///
+/// The work item references the device object, and the driver cannot be unloaded until the
+/// closure has run. Returns [`Error::DELETE_PENDING`] if the driver is being unloaded, and
+/// [`Error::INSUFFICIENT_RESOURCES`] if the work item could not be allocated. This function can
+/// be called at `IRQL <= DISPATCH_LEVEL`.
+///
+/// ```ignore
+/// workitem::queue(&device, WorkQueue::Delayed, move || {
+///     process(request);
+/// })?;
+/// ```
+pub fn queue<F>(device: &Device, queue: WorkQueue, f: F) -> Result<(), Error>
+where
+    F: FnOnce() + Send + 'static,
+{
+    crate::debug_assert_irql!(<= DISPATCH_LEVEL);
+
+    let rundown = DRIVER_RUNDOWN.acquire()
+        .ok_or(Error::DELETE_PENDING)?;
+
+    let work_item = unsafe {
+        IoAllocateWorkItem(device.as_raw_mut())
+    };
+
+    if work_item.is_null() {
+        return Err(Error::INSUFFICIENT_RESOURCES);
+    }
+
+    let context = Box::into_raw(Box::new(Context {
+        f,
+        _rundown: rundown,
+    }));
+
+    unsafe {
+        IoQueueWorkItemEx(
+            work_item,
+            Some(work_item_routine::<F>),
+            queue.as_raw(),
+            context as _,
+        );
+    }
+
+    Ok(())
+}
@@ -63,6 +63,7 @@ fn generate_ntoskrnl() {
     println!("cargo:rerun-if-changed=src/wrapper.h");
     println!("cargo:rerun-if-changed=src/wrapper.c");
     println!("cargo:rustc-link-lib=ntoskrnl");
+    println!("cargo:rustc-link-lib=wdmsec");
 
     let include_dir = windows_kernel_build::get_km_dir(DirectoryType::Include).unwrap();
     let out_path = PathBuf::from(
@@ -7,6 +7,10 @@ pub use cty::*;
 include!(concat!(env!("OUT_DIR"), "/base.rs"));
 
 pub const STATUS_SUCCESS:                  NTSTATUS = 0x00000000;
+pub const STATUS_WAIT_0:                   NTSTATUS = 0x00000000;
+pub const STATUS_ABANDONED_WAIT_0:         NTSTATUS = 0x00000080;
+pub const STATUS_TIMEOUT:                  NTSTATUS = 0x00000102;
+pub const STATUS_PENDING:                  NTSTATUS = 0x00000103;
 pub const STATUS_GUARD_PAGE_VIOLATION:     NTSTATUS = 0x80000001 as u32 as i32;
 pub const STATUS_DATATYPE_MISALIGNMENT:    NTSTATUS = 0x80000002 as u32 as i32;
 pub const STATUS_BREAKPOINT:               NTSTATUS = 0x80000003 as u32 as i32;
@@ -18,11 +22,16 @@ pub const STATUS_ACCESS_VIOLATION:         NTSTATUS = 0xC0000005 as u32 as i32;
 pub const STATUS_IN_PAGE_ERROR:            NTSTATUS = 0xC0000006 as u32 as i32;
 pub const STATUS_INVALID_HANDLE:           NTSTATUS = 0xC0000008 as u32 as i32;
 pub const STATUS_INVALID_PARAMETER:        NTSTATUS = 0xC000000D as u32 as i32;
+pub const STATUS_INVALID_DEVICE_REQUEST:   NTSTATUS = 0xC0000010 as u32 as i32;
 pub const STATUS_END_OF_FILE:              NTSTATUS = 0xC0000011 as u32 as i32;
+pub const STATUS_MORE_PROCESSING_REQUIRED: NTSTATUS = 0xC0000016 as u32 as i32;
 pub const STATUS_NO_MEMORY:                NTSTATUS = 0xC0000017 as u32 as i32;
 pub const STATUS_ILLEGAL_INSTRUCTION:      NTSTATUS = 0xC000001D as u32 as i32;
+pub const STATUS_ACCESS_DENIED:            NTSTATUS = 0xC0000022 as u32 as i32;
 pub const STATUS_NONCONTINUABLE_EXCEPTION: NTSTATUS = 0xC0000025 as u32 as i32;
 pub const STATUS_INVALID_DISPOSITION:      NTSTATUS = 0xC0000026 as u32 as i32;
+pub const STATUS_SEMAPHORE_LIMIT_EXCEEDED: NTSTATUS = 0xC0000047 as u32 as i32;
+pub const STATUS_DELETE_PENDING:           NTSTATUS = 0xC0000056 as u32 as i32;
 pub const STATUS_ARRAY_BOUNDS_EXCEEDED:    NTSTATUS = 0xC000008C as u32 as i32;
 pub const STATUS_FLOAT_DENORMAL_OPERAND:   NTSTATUS = 0xC000008D as u32 as i32;
 pub const STATUS_FLOAT_DIVIDE_BY_ZERO:     NTSTATUS = 0xC000008E as u32 as i32;
@@ -35,5 +44,8 @@ pub const STATUS_INTEGER_DIVIDE_BY_ZERO:   NTSTATUS = 0xC0000094 as u32 as i32;
 pub const STATUS_INTEGER_OVERFLOW:         NTSTATUS = 0xC0000095 as u32 as i32;
 pub const STATUS_PRIVILEGED_INSTRUCTION:   NTSTATUS = 0xC0000096 as u32 as i32;
 pub const STATUS_INSUFFICIENT_RESOURCES:   NTSTATUS = 0xC000009A as u32 as i32;
+pub const STATUS_IO_TIMEOUT:               NTSTATUS = 0xC00000B5 as u32 as i32;
+pub const STATUS_NOT_SUPPORTED:            NTSTATUS = 0xC00000BB as u32 as i32;
 pub const STATUS_INVALID_USER_BUFFER:      NTSTATUS = 0xC00000E8 as u32 as i32;
+pub const STATUS_CANCELLED:                NTSTATUS = 0xC0000120 as u32 as i32;
 pub const STATUS_STACK_OVERFLOW:           NTSTATUS = 0xC00000FD as u32 as i32;
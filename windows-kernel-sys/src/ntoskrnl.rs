@@ -11,6 +11,18 @@ extern "C" {
     pub fn _ExReleasePushLockExclusive(push_lock: PEX_PUSH_LOCK);
     pub fn _ExAcquirePushLockShared(push_lock: PEX_PUSH_LOCK);
     pub fn _ExReleasePushLockShared(push_lock: PEX_PUSH_LOCK);
+    pub fn _ExTryAcquirePushLockExclusive(push_lock: PEX_PUSH_LOCK) -> BOOLEAN;
+    pub fn _ExTryAcquirePushLockShared(push_lock: PEX_PUSH_LOCK) -> BOOLEAN;
+    pub fn _KeInitializeSpinLock(spin_lock: PKSPIN_LOCK);
+    pub fn _KeAcquireSpinLock(spin_lock: PKSPIN_LOCK, old_irql: PKIRQL);
+    pub fn _KeGetCurrentIrql() -> KIRQL;
+    pub fn _KeRaiseIrql(new_irql: KIRQL, old_irql: PKIRQL);
+    pub fn _KeLowerIrql(new_irql: KIRQL);
+    pub fn _KeGetCurrentThread() -> PKTHREAD;
+    pub fn _KeQueryInterruptTime() -> ULONG64;
+    pub fn _ExInterlockedPushEntrySList(head: PSLIST_HEADER, entry: PSLIST_ENTRY) -> PSLIST_ENTRY;
+    pub fn _InterlockedPopEntrySList(head: PSLIST_HEADER) -> PSLIST_ENTRY;
+    pub fn _InterlockedFlushSList(head: PSLIST_HEADER) -> PSLIST_ENTRY;
     pub fn _IoGetCurrentIrpStackLocation(irp: PIRP) -> PIO_STACK_LOCATION;
     pub fn _IoGetNextIrpStackLocation(irp: PIRP) -> PIO_STACK_LOCATION;
     pub fn _IoSetCompletionRoutine(
@@ -21,12 +33,35 @@ extern "C" {
         invoke_on_error: BOOLEAN,
         invoke_on_cancel: BOOLEAN,
     );
+    pub fn _IoSkipCurrentIrpStackLocation(irp: PIRP);
+    pub fn _IoCopyCurrentIrpStackLocationToNext(irp: PIRP);
+    pub fn _IoCallDriver(device: PDEVICE_OBJECT, irp: PIRP) -> NTSTATUS;
+    pub fn _IoMarkIrpPending(irp: PIRP);
     pub fn _IoCompleteRequest(irp: PIRP, priority_boost: CCHAR);
+    pub fn _IoCreateDeviceSecure(
+        driver_object: PDRIVER_OBJECT,
+        device_extension_size: ULONG,
+        device_name: PUNICODE_STRING,
+        device_type: DEVICE_TYPE,
+        device_characteristics: ULONG,
+        exclusive: BOOLEAN,
+        default_sddl_string: PCUNICODE_STRING,
+        device_class_guid: LPCGUID,
+        device_object: *mut PDEVICE_OBJECT,
+    ) -> NTSTATUS;
     pub fn _MmGetMdlByteCount(mdl: PMDL) -> ULONG;
     pub fn _MmGetMdlByteOffset(mdl: PMDL) -> ULONG;
     pub fn _MmGetSystemAddressForMdlSafe(mdl: PMDL, priority: ULONG) -> PVOID;
     pub fn _ObDereferenceObject(p: *mut cty::c_void);
     pub fn _ObReferenceObject(p: *mut cty::c_void);
+    pub fn _CopyFromUser(dst: PVOID, src: *const cty::c_void, size: SIZE_T, probe: BOOLEAN) -> NTSTATUS;
+    pub fn _CopyToUser(dst: PVOID, src: *const cty::c_void, size: SIZE_T, probe: BOOLEAN) -> NTSTATUS;
+    pub fn _KeReleaseSemaphoreSafe(
+        semaphore: PKSEMAPHORE,
+        increment: KPRIORITY,
+        adjustment: LONG,
+        previous_count: PLONG,
+    ) -> NTSTATUS;
 }
 
 pub use self::_ExInitializeFastMutex as ExInitializeFastMutex;
@@ -34,15 +69,35 @@ pub use self::_ExAcquirePushLockExclusive as ExAcquirePushLockExclusive;
 pub use self::_ExReleasePushLockExclusive as ExReleasePushLockExclusive;
 pub use self::_ExAcquirePushLockShared as ExAcquirePushLockShared;
 pub use self::_ExReleasePushLockShared as ExReleasePushLockShared;
+pub use self::_ExTryAcquirePushLockExclusive as ExTryAcquirePushLockExclusive;
+pub use self::_ExTryAcquirePushLockShared as ExTryAcquirePushLockShared;
+pub use self::_KeInitializeSpinLock as KeInitializeSpinLock;
+pub use self::_KeAcquireSpinLock as KeAcquireSpinLock;
+pub use self::_KeGetCurrentIrql as KeGetCurrentIrql;
+pub use self::_KeRaiseIrql as KeRaiseIrql;
+pub use self::_KeLowerIrql as KeLowerIrql;
+pub use self::_KeGetCurrentThread as KeGetCurrentThread;
+pub use self::_KeQueryInterruptTime as KeQueryInterruptTime;
+pub use self::_ExInterlockedPushEntrySList as ExInterlockedPushEntrySList;
+pub use self::_InterlockedPopEntrySList as InterlockedPopEntrySList;
+pub use self::_InterlockedFlushSList as InterlockedFlushSList;
 pub use self::_IoGetCurrentIrpStackLocation as IoGetCurrentIrpStackLocation;
 pub use self::_IoGetNextIrpStackLocation as IoGetNextIrpStackLocation;
 pub use self::_IoSetCompletionRoutine as IoSetCompletionRoutine;
+pub use self::_IoSkipCurrentIrpStackLocation as IoSkipCurrentIrpStackLocation;
+pub use self::_IoCopyCurrentIrpStackLocationToNext as IoCopyCurrentIrpStackLocationToNext;
+pub use self::_IoCallDriver as IoCallDriver;
+pub use self::_IoMarkIrpPending as IoMarkIrpPending;
 pub use self::_IoCompleteRequest as IoCompleteRequest;
+pub use self::_IoCreateDeviceSecure as IoCreateDeviceSecure;
 pub use self::_MmGetMdlByteCount as MmGetMdlByteCount;
 pub use self::_MmGetMdlByteOffset as MmGetMdlByteOffset;
 pub use self::_MmGetSystemAddressForMdlSafe as MmGetSystemAddressForMdlSafe;
 pub use self::_ObDereferenceObject as ObDereferenceObject;
 pub use self::_ObReferenceObject as ObReferenceObject;
+pub use self::_CopyFromUser as CopyFromUser;
+pub use self::_CopyToUser as CopyToUser;
+pub use self::_KeReleaseSemaphoreSafe as KeReleaseSemaphoreSafe;
 
 pub use self::IoGetCurrentProcess as PsGetCurrentProcess;
 
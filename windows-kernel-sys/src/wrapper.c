@@ -30,6 +30,75 @@ void _ExReleasePushLockShared(
 	ExReleasePushLockShared(push_lock);
 }
 
+BOOLEAN _ExTryAcquirePushLockExclusive(
+	PEX_PUSH_LOCK push_lock
+) {
+	return ExTryAcquirePushLockExclusiveEx(push_lock, EX_DEFAULT_PUSH_LOCK_FLAGS);
+}
+
+BOOLEAN _ExTryAcquirePushLockShared(
+	PEX_PUSH_LOCK push_lock
+) {
+	return ExTryAcquirePushLockSharedEx(push_lock, EX_DEFAULT_PUSH_LOCK_FLAGS);
+}
+
+void _KeInitializeSpinLock(
+	PKSPIN_LOCK spin_lock
+) {
+	KeInitializeSpinLock(spin_lock);
+}
+
+void _KeAcquireSpinLock(
+	PKSPIN_LOCK spin_lock,
+	PKIRQL old_irql
+) {
+	KeAcquireSpinLock(spin_lock, old_irql);
+}
+
+KIRQL _KeGetCurrentIrql(void) {
+	return KeGetCurrentIrql();
+}
+
+void _KeRaiseIrql(
+	KIRQL new_irql,
+	PKIRQL old_irql
+) {
+	KeRaiseIrql(new_irql, old_irql);
+}
+
+void _KeLowerIrql(
+	KIRQL new_irql
+) {
+	KeLowerIrql(new_irql);
+}
+
+PKTHREAD _KeGetCurrentThread(void) {
+	return KeGetCurrentThread();
+}
+
+ULONG64 _KeQueryInterruptTime(void) {
+	return KeQueryInterruptTime();
+}
+
+PSLIST_ENTRY _ExInterlockedPushEntrySList(
+	PSLIST_HEADER head,
+	PSLIST_ENTRY entry
+) {
+	return ExInterlockedPushEntrySList(head, entry, NULL);
+}
+
+PSLIST_ENTRY _InterlockedPopEntrySList(
+	PSLIST_HEADER head
+) {
+	return InterlockedPopEntrySList(head);
+}
+
+PSLIST_ENTRY _InterlockedFlushSList(
+	PSLIST_HEADER head
+) {
+	return InterlockedFlushSList(head);
+}
+
 PIO_STACK_LOCATION _IoGetCurrentIrpStackLocation(PIRP irp) {
 	return IoGetCurrentIrpStackLocation(irp);
 }
@@ -49,6 +118,25 @@ void _IoSetCompletionRoutine(
 	IoSetCompletionRoutine(irp, completion_routine, context, invoke_on_success, invoke_on_error, invoke_on_cancel);
 }
 
+void _IoSkipCurrentIrpStackLocation(PIRP irp) {
+	IoSkipCurrentIrpStackLocation(irp);
+}
+
+void _IoCopyCurrentIrpStackLocationToNext(PIRP irp) {
+	IoCopyCurrentIrpStackLocationToNext(irp);
+}
+
+NTSTATUS _IoCallDriver(
+	PDEVICE_OBJECT device,
+	PIRP irp
+) {
+	return IoCallDriver(device, irp);
+}
+
+void _IoMarkIrpPending(PIRP irp) {
+	IoMarkIrpPending(irp);
+}
+
 void _IoCompleteRequest(
 	PIRP irp,
 	CCHAR priority_boost
@@ -56,6 +144,30 @@ void _IoCompleteRequest(
 	IoCompleteRequest(irp, priority_boost);
 }
 
+NTSTATUS _IoCreateDeviceSecure(
+	PDRIVER_OBJECT driver_object,
+	ULONG device_extension_size,
+	PUNICODE_STRING device_name,
+	DEVICE_TYPE device_type,
+	ULONG device_characteristics,
+	BOOLEAN exclusive,
+	PCUNICODE_STRING default_sddl_string,
+	LPCGUID device_class_guid,
+	PDEVICE_OBJECT *device_object
+) {
+	return IoCreateDeviceSecure(
+		driver_object,
+		device_extension_size,
+		device_name,
+		device_type,
+		device_characteristics,
+		exclusive,
+		default_sddl_string,
+		device_class_guid,
+		device_object
+	);
+}
+
 ULONG _MmGetMdlByteCount(PMDL mdl) {
 	return MmGetMdlByteCount(mdl);
 }
@@ -75,3 +187,56 @@ void _ObDereferenceObject(PVOID p) {
 void _ObReferenceObject(PVOID p) {
 	ObReferenceObject(p);
 }
+
+NTSTATUS _CopyFromUser(
+	PVOID dst,
+	const VOID *src,
+	SIZE_T size,
+	BOOLEAN probe
+) {
+	__try {
+		if (probe) {
+			ProbeForRead((PVOID)src, size, 1);
+		}
+
+		RtlCopyMemory(dst, src, size);
+	} __except (EXCEPTION_EXECUTE_HANDLER) {
+		return GetExceptionCode();
+	}
+
+	return STATUS_SUCCESS;
+}
+
+NTSTATUS _CopyToUser(
+	PVOID dst,
+	const VOID *src,
+	SIZE_T size,
+	BOOLEAN probe
+) {
+	__try {
+		if (probe) {
+			ProbeForWrite(dst, size, 1);
+		}
+
+		RtlCopyMemory(dst, src, size);
+	} __except (EXCEPTION_EXECUTE_HANDLER) {
+		return GetExceptionCode();
+	}
+
+	return STATUS_SUCCESS;
+}
+
+NTSTATUS _KeReleaseSemaphoreSafe(
+	PKSEMAPHORE semaphore,
+	KPRIORITY increment,
+	LONG adjustment,
+	PLONG previous_count
+) {
+	__try {
+		*previous_count = KeReleaseSemaphore(semaphore, increment, adjustment, FALSE);
+	} __except (EXCEPTION_EXECUTE_HANDLER) {
+		return GetExceptionCode();
+	}
+
+	return STATUS_SUCCESS;
+}
@@ -61,3 +61,4 @@ typedef union _KIDTENTRY64
 } KIDTENTRY64, *PKIDTENTRY64;
 
 #include "ntifs.h"
+#include "wdmsec.h"
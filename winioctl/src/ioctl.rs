@@ -6,6 +6,9 @@ use winapi::um::winioctl::{
 use winapi::um::winnt::{
     FILE_READ_DATA, FILE_WRITE_DATA,
 };
+use windows_kernel_common::pod::Pod;
+use crate::error::Error;
+use crate::DeviceIoControl;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum DeviceType {
@@ -415,3 +418,162 @@ macro_rules! ioctl_readwrite {
         }
     }
 }
+
+/// Sends the I/O control code along with the input to the device, and returns the output, which
+/// the driver must have written in full. This is used by the functions that [`ioctl_table!`]
+/// generates.
+///
+/// # Safety
+///
+/// The handle must be a valid handle to the device.
+#[doc(hidden)]
+pub unsafe fn device_io_control<I: Pod, O: Pod>(
+    handle: *mut std::ffi::c_void,
+    code: u32,
+    input: &I,
+) -> Result<O, Error> {
+    // Any bit pattern is a valid `O`, hence the zeroed output is valid even if the driver does
+    // not write to it.
+    let mut output = std::mem::MaybeUninit::<O>::zeroed();
+    let mut return_value = 0;
+
+    let input_size = std::mem::size_of::<I>();
+    let output_size = std::mem::size_of::<O>();
+
+    let input_ptr = match input_size {
+        0 => std::ptr::null_mut(),
+        _ => input as *const I as *mut std::ffi::c_void,
+    };
+
+    let output_ptr = match output_size {
+        0 => std::ptr::null_mut(),
+        _ => output.as_mut_ptr() as *mut std::ffi::c_void,
+    };
+
+    let status = DeviceIoControl(
+        handle as _,
+        code,
+        input_ptr as _,
+        input_size as _,
+        output_ptr as _,
+        output_size as _,
+        &mut return_value,
+        std::ptr::null_mut(),
+    ) != 0;
+
+    if !status {
+        return Err(std::io::Error::last_os_error().into());
+    }
+
+    // The driver must have written the full output.
+    if (return_value as usize) < output_size {
+        return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+    }
+
+    Ok(output.assume_init())
+}
+
+/// Declares a set of I/O control codes along with their input and output types. The same
+/// declaration can be used with the `ioctl_table!` macro of the `windows-kernel-rs` crate to
+/// generate the dispatch trait of the driver, e.g. by keeping the declaration in a separate file
+/// that is included by both the driver and the user mode application through [`include!`].
+///
+/// ```ignore
+/// ioctl_table! {
+///     pub ExampleIoctls: Unknown {
+///         print_value(0x800, ANY_ACCESS, Buffered): () => ();
+///         read_value(0x801, READ_DATA, Buffered): () => u32;
+///         write_value(0x802, WRITE_DATA, Buffered): u32 => ();
+///     }
+/// }
+/// ```
+///
+/// In user mode this generates a struct that wraps the handle to the device, with a function for
+/// every I/O control code that sends the input to the device and returns the output. The input
+/// and output types must implement [`Pod`]. Functions for I/O control codes without input, i.e.
+/// with `()` as the input type, do not take an argument:
+///
+/// ```ignore
+/// let ioctls = unsafe { ExampleIoctls::new(file.as_raw_handle()) };
+///
+/// let value = ioctls.read_value()?;
+/// ioctls.write_value(&(value + 1))?;
+/// ```
+#[macro_export]
+macro_rules! ioctl_table {
+    (@methods $table:ident $dev_ty:ident) => {};
+    (
+        @methods $table:ident $dev_ty:ident
+        $(#[$attr:meta])*
+        $name:ident($nr:expr, $access:ident, $method:ident): () => $output:ty;
+        $($rest:tt)*
+    ) => {
+        impl $table {
+            $(#[$attr])*
+            pub fn $name(&self) -> Result<$output, $crate::Error> {
+                let code = $crate::ControlCode(
+                    $crate::DeviceType::$dev_ty,
+                    $crate::RequiredAccess::$access,
+                    $nr,
+                    $crate::TransferMethod::$method,
+                ).into();
+
+                unsafe {
+                    $crate::device_io_control::<(), $output>(self.handle, code, &())
+                }
+            }
+        }
+
+        $crate::ioctl_table!(@methods $table $dev_ty $($rest)*);
+    };
+    (
+        @methods $table:ident $dev_ty:ident
+        $(#[$attr:meta])*
+        $name:ident($nr:expr, $access:ident, $method:ident): $input:ty => $output:ty;
+        $($rest:tt)*
+    ) => {
+        impl $table {
+            $(#[$attr])*
+            pub fn $name(&self, input: &$input) -> Result<$output, $crate::Error> {
+                let code = $crate::ControlCode(
+                    $crate::DeviceType::$dev_ty,
+                    $crate::RequiredAccess::$access,
+                    $nr,
+                    $crate::TransferMethod::$method,
+                ).into();
+
+                unsafe {
+                    $crate::device_io_control::<$input, $output>(self.handle, code, input)
+                }
+            }
+        }
+
+        $crate::ioctl_table!(@methods $table $dev_ty $($rest)*);
+    };
+    (
+        $(#[$table_attr:meta])*
+        $vis:vis $table:ident: $dev_ty:ident {
+            $($entries:tt)*
+        }
+    ) => {
+        $(#[$table_attr])*
+        $vis struct $table {
+            handle: *mut std::ffi::c_void,
+        }
+
+        impl $table {
+            /// Wraps the handle to the device.
+            ///
+            /// # Safety
+            ///
+            /// The handle must be a valid handle to the device for as long as the table exists.
+            pub unsafe fn new(handle: *mut std::ffi::c_void) -> Self {
+                Self {
+                    handle,
+                }
+            }
+        }
+
+        $crate::ioctl_table!(@methods $table $dev_ty $($entries)*);
+    };
+}
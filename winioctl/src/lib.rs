@@ -3,5 +3,9 @@ mod ioctl;
 
 pub use crate::error::Error;
 pub use crate::ioctl::{ControlCode, DeviceType, RequiredAccess, TransferMethod};
+#[doc(hidden)]
+pub use crate::ioctl::device_io_control;
+pub use windows_kernel_common::pod::{self, Pod};
+pub use windows_kernel_derive::Pod;
 
 pub use winapi::um::ioapiset::DeviceIoControl;